@@ -1,3 +1,27 @@
+use crate::AppResult;
+use crate::vault::{frame::Padding, passphrase, suite::Suite};
+
+/// Default maximum size of data accepted for encryption, 1 MiB
+const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+/// Default maximum size of streamed data accepted for encryption, 1 GiB
+const DEFAULT_MAX_STREAM_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Default maximum size of decompressed data, 16 MiB
+const DEFAULT_MAX_DECOMPRESSED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Default shortest validity of a claw, 1 minute
+const DEFAULT_MIN_VALIDITY: i32 = 60;
+
+/// Default longest validity of a claw, 7 days
+const DEFAULT_MAX_VALIDITY: i32 = 7 * 24 * 60 * 60;
+
+/// Default longest time a claw may stay locked, 30 days
+const DEFAULT_MAX_LOCK: i64 = 30 * 24 * 60 * 60;
+
+/// Default highest Argon2id memory cost of a passphrase, 64 MiB in KiB
+const DEFAULT_MAX_KDF_MEMCOST: u32 = 64 * 1024;
+
 pub struct Config {}
 
 impl Config {
@@ -9,4 +33,147 @@ impl Config {
     pub fn get_db_url() -> String {
         std::env::var("DATABASE_URL").expect("Missing db url")
     }
+
+    /// Returns maximum size of data in bytes accepted for encryption
+    ///
+    /// Configured via `MAX_PAYLOAD_BYTES`, defaults to 1 MiB
+    pub fn get_max_payload_bytes() -> usize {
+        std::env::var("MAX_PAYLOAD_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES)
+    }
+
+    /// Returns maximum size of streamed data in bytes accepted for encryption
+    ///
+    /// Configured via `MAX_STREAM_BYTES`, defaults to 1 GiB
+    pub fn get_max_stream_bytes() -> u64 {
+        std::env::var("MAX_STREAM_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_STREAM_BYTES)
+    }
+
+    /// Returns shortest validity in seconds a claw may be requested with
+    ///
+    /// Configured via `MIN_VALIDITY_SECS`, defaults to 1 minute
+    pub fn get_min_validity() -> i32 {
+        std::env::var("MIN_VALIDITY_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_MIN_VALIDITY)
+    }
+
+    /// Returns longest validity in seconds a claw may be requested with
+    ///
+    /// Configured via `MAX_VALIDITY_SECS`, defaults to 7 days, and never
+    /// shorter than [`Config::get_min_validity`]
+    pub fn get_max_validity() -> i32 {
+        std::env::var("MAX_VALIDITY_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_MAX_VALIDITY)
+            .max(Self::get_min_validity())
+    }
+
+    /// Returns longest time in seconds a claw may be locked with `not_before`
+    ///
+    /// Configured via `MAX_LOCK_SECS`, defaults to 30 days
+    pub fn get_max_lock() -> i64 {
+        std::env::var("MAX_LOCK_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_MAX_LOCK)
+    }
+
+    /// Returns highest Argon2id memory cost in KiB a passphrase may be
+    /// stretched with when a claw is decrypted
+    ///
+    /// Configured via `MAX_KDF_MEMCOST_KIB`, defaults to 64 MiB, and never
+    /// below [`passphrase::MEMCOST`] new claws are protected with
+    pub fn get_max_kdf_memcost() -> u32 {
+        std::env::var("MAX_KDF_MEMCOST_KIB")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_KDF_MEMCOST)
+            .max(passphrase::MEMCOST)
+    }
+
+    /// Returns versioned key-encryption keys, if any
+    ///
+    /// Configured via `CLAW_KEKS` as `<version>:<base64 key>` pairs separated
+    /// by commas, e.g. `1:<key>,2:<key>`
+    pub fn get_keks() -> Option<String> {
+        std::env::var("CLAW_KEKS").ok().filter(|v| !v.trim().is_empty())
+    }
+
+    /// Returns version of the key-encryption key wrapping new claws
+    ///
+    /// Configured via `CLAW_ACTIVE_KEK`, defaults to the highest configured version
+    pub fn get_active_kek() -> Option<i16> {
+        std::env::var("CLAW_ACTIVE_KEK").ok().and_then(|v| v.parse().ok())
+    }
+
+    /// Returns whether the server may receive plaintext for encryption
+    ///
+    /// Configured via `PLAINTEXT_ENCRYPTION`, defaults to `true`. When disabled
+    /// only client-side sealed payloads are accepted.
+    pub fn is_plaintext_enabled() -> bool {
+        std::env::var("PLAINTEXT_ENCRYPTION").map(|v| v != "false").unwrap_or(true)
+    }
+
+    /// Returns whether the validity of a time-locked claw starts when it unlocks
+    ///
+    /// Configured via `EXPIRY_FROM_UNLOCK`, defaults to `true`. When disabled
+    /// the validity starts when the claw is created, so the claw is readable
+    /// for whatever is left of it after unlocking.
+    pub fn is_expiry_from_unlock() -> bool {
+        std::env::var("EXPIRY_FROM_UNLOCK").map(|v| v != "false").unwrap_or(true)
+    }
+
+    /// Returns whether data is compressed before encryption
+    ///
+    /// Configured via `COMPRESSION`, defaults to `false`. Compression leaks
+    /// how well the data compresses through the size of the claw, so keep it
+    /// disabled when an attacker may control part of the encrypted data.
+    pub fn is_compression_enabled() -> bool {
+        std::env::var("COMPRESSION").map(|v| v == "true").unwrap_or(false)
+    }
+
+    /// Returns maximum size of data in bytes a compressed claw may decompress to
+    ///
+    /// Configured via `MAX_DECOMPRESSED_BYTES`, defaults to 16 MiB
+    pub fn get_max_decompressed_bytes() -> usize {
+        std::env::var("MAX_DECOMPRESSED_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_DECOMPRESSED_BYTES)
+    }
+
+    /// Returns size buckets claw data is padded to before encryption
+    ///
+    /// Configured via `CLAW_PADDING` as `none`, `pow2` for powers of two or a
+    /// step in bytes such as `1024`, defaults to `none`
+    pub fn get_padding() -> AppResult<Padding> {
+        std::env::var("CLAW_PADDING").map_or(Ok(Padding::None), |v| v.parse())
+    }
+
+    /// Returns cipher suite new claws are encrypted with unless requested otherwise
+    ///
+    /// Configured via `CLAW_SUITE`, defaults to `aes-256-gcm`
+    pub fn get_suite() -> AppResult<Suite> {
+        std::env::var("CLAW_SUITE").map_or(Ok(Suite::default()), |v| v.parse())
+    }
+
+    /// Returns cipher suites requests may choose from
+    ///
+    /// Configured via `CLAW_ALLOWED_SUITES` as suites separated by commas,
+    /// defaults to all suites
+    pub fn get_allowed_suites() -> AppResult<Vec<Suite>> {
+        std::env::var("CLAW_ALLOWED_SUITES")
+            .map_or(Ok(Suite::ALL.to_vec()), |v| v.split(',').map(str::parse).collect())
+    }
 }
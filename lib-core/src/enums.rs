@@ -1,31 +1,128 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy)]
-pub enum ValidDuration {
-    Minute,
-    QuarterHour,
-    HalfHour,
-}
+use crate::{AppError, AppResult, ErrType, config::Config};
+
+/// Time a claw stays valid for, in seconds
+///
+/// Requested as a number of seconds or an ISO-8601 duration such as `PT15M`
+/// or `P2DT12H`, within the bounds configured via [`Config::get_min_validity`]
+/// and [`Config::get_max_validity`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidDuration(i32);
 
 impl ValidDuration {
+    /// Checks `secs` against the configured bounds
+    pub fn new(secs: i64) -> AppResult<Self> {
+        let (min, max) = (Config::get_min_validity(), Config::get_max_validity());
+        if secs < min as i64 || secs > max as i64 {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                format!("Validity must be between {} and {} seconds", min, max),
+            ));
+        }
+        Ok(ValidDuration(secs as i32))
+    }
+
     /// Returns duration in seconds
     pub fn get_duration(&self) -> i32 {
-        match self {
-            ValidDuration::Minute => 60,
-            ValidDuration::QuarterHour => 900,
-            ValidDuration::HalfHour => 1800,
+        self.0
+    }
+}
+
+impl Display for ValidDuration {
+    /// Writes human readable string, e.g. `1 day 2 hours`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0;
+        let units = [
+            (secs / 86400, "day"),
+            (secs % 86400 / 3600, "hour"),
+            (secs % 3600 / 60, "minute"),
+            (secs % 60, "second"),
+        ];
+        let parts: Vec<String> = units
+            .into_iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, unit)| format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" }))
+            .collect();
+        f.write_str(&parts.join(" "))
+    }
+}
+
+impl FromStr for ValidDuration {
+    type Err = AppError;
+
+    /// Parses seconds or an ISO-8601 duration, see [`parse_iso_duration`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let secs = match s.parse::<i64>() {
+            Ok(secs) => Some(secs),
+            Err(_) => parse_iso_duration(s),
+        };
+        let secs = secs.ok_or_else(|| {
+            AppError::new(ErrType::BadRequest, format!("Invalid validity: {}", s))
+        })?;
+        ValidDuration::new(secs)
+    }
+}
+
+impl TryFrom<i32> for ValidDuration {
+    type Error = AppError;
+
+    /// Takes a stored validity, which is not checked against the configured
+    /// bounds as they may have changed since
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        if value <= 0 {
+            return Err(AppError::new(
+                ErrType::ServerError,
+                format!("Invalid stored validity: {}", value),
+            ));
         }
+        Ok(ValidDuration(value))
     }
+}
 
-    /// Returns human readable string
-    pub fn to_string(&self) -> String {
-        match self {
-            ValidDuration::Minute => "1 minute",
-            ValidDuration::QuarterHour => "15 minutes",
-            ValidDuration::HalfHour => "30 minutes",
+/// Returns seconds of ISO-8601 duration `s` such as `P1W`, `P2DT3H` or `PT90S`
+///
+/// Years and months have no fixed length and are rejected, as are fractions.
+fn parse_iso_duration(s: &str) -> Option<i64> {
+    let s = s.to_ascii_uppercase();
+    let rest = s.strip_prefix('P')?;
+    if rest.is_empty() || rest.ends_with('T') {
+        return None;
+    }
+
+    let (mut secs, mut time, mut last) = (0i64, false, 0);
+    let mut value = String::new();
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            value.push(c);
+            continue;
+        }
+        if c == 'T' && !time && value.is_empty() {
+            time = true;
+            continue;
+        }
+
+        // designators must appear once each and in order
+        let (order, unit) = match (time, c) {
+            (false, 'W') => (1, 604800),
+            (false, 'D') => (2, 86400),
+            (true, 'H') => (3, 3600),
+            (true, 'M') => (4, 60),
+            (true, 'S') => (5, 1),
+            _ => return None,
+        };
+        if order <= last || value.is_empty() {
+            return None;
         }
-        .into()
+        last = order;
+        secs = value.parse::<i64>().ok()?.checked_mul(unit)?.checked_add(secs)?;
+        value.clear();
     }
+    value.is_empty().then_some(secs)
 }
 
 impl Serialize for ValidDuration {
@@ -36,18 +133,35 @@ impl Serialize for ValidDuration {
         serializer.serialize_i32(self.get_duration())
     }
 }
+
 impl<'de> Deserialize<'de> for ValidDuration {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let d = i16::deserialize(deserializer)?;
-        match d {
-            60 => Ok(Self::Minute),
-            900 => Ok(Self::QuarterHour),
-            1800 => Ok(Self::HalfHour),
-            _ => Err(serde::de::Error::custom("Invalid duration value")),
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = ValidDuration;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("seconds or an ISO-8601 duration")
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                ValidDuration::new(v).map_err(|e| E::custom(e.get_messages().0))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                self.visit_i64(v.try_into().unwrap_or(i64::MAX))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(|e: AppError| E::custom(e.get_messages().0))
+            }
         }
+
+        deserializer.deserialize_any(Visitor)
     }
 }
 
@@ -58,20 +172,30 @@ impl utoipa::ToSchema for ValidDuration {
 }
 impl utoipa::PartialSchema for ValidDuration {
     fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
-        utoipa::openapi::ObjectBuilder::new()
-            .schema_type(utoipa::openapi::schema::Type::Integer)
-            .enum_values::<[i16; 3usize], i16>(Some([60, 900, 1800]))
-            .description(Some("Supported valid durations"))
-            .into()
-    }
-}
+        use utoipa::openapi::{ObjectBuilder, OneOfBuilder, schema::Type};
 
-impl From<i32> for ValidDuration {
-    fn from(value: i32) -> Self {
-        match value {
-            900 => Self::QuarterHour,
-            1800 => Self::HalfHour,
-            _ => Self::Minute,
-        }
+        let (min, max) = (Config::get_min_validity(), Config::get_max_validity());
+        OneOfBuilder::new()
+            .item(
+                ObjectBuilder::new()
+                    .schema_type(Type::Integer)
+                    .minimum(Some(min))
+                    .maximum(Some(max))
+                    .description(Some("Seconds")),
+            )
+            .item(
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .format(Some(utoipa::openapi::SchemaFormat::Custom("duration".into())))
+                    .examples(["PT15M", "P2DT12H"])
+                    .description(Some(
+                        "ISO-8601 duration in weeks, days, hours, minutes and seconds",
+                    )),
+            )
+            .description(Some(format!(
+                "Time the claw stays valid for, between {} and {} seconds",
+                min, max
+            )))
+            .into()
     }
 }
@@ -1,81 +1,21 @@
 use std::{error::Error, fmt::Display};
 
-use axum::{
-    extract::{FromRequest, Request, rejection::JsonRejection},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
-use interceptor::ReqId;
-use serde::Serialize;
-use utoipa::ToSchema;
-use validator::Validate;
-
 pub mod config;
+#[cfg(feature = "server")]
 pub mod enums;
+#[cfg(feature = "server")]
 pub mod interceptor;
+#[cfg(feature = "server")]
+mod response;
 pub mod vault;
 
-#[derive(Serialize, ToSchema)]
-pub struct EmptyResponse {
-    status: u16,
-    message: String,
-}
-impl EmptyResponse {
-    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
-        EmptyResponse { status: status.as_u16(), message: message.into() }
-    }
-}
-
-/// Custom Json wrapper handling json payload
-/// parsing errors.
-///
-/// See more: [`axum::Json`] [`validator`]
-pub struct Json<T>(pub T);
-
-impl<T> IntoResponse for Json<T>
-where
-    axum::Json<T>: IntoResponse,
-{
-    fn into_response(self) -> Response {
-        (StatusCode::OK, axum::Json(self.0)).into_response()
-    }
-}
-
-/// Custom Json wrapper handling json payload
-///
-/// Struct being extract must have [`serde::Deserialize`] and [`validator::Validate`] to validate the payload
-impl<S, T> FromRequest<S> for Json<T>
-where
-    axum::Json<T>: FromRequest<S, Rejection = JsonRejection>,
-    T: Validate,
-    S: Send + Sync,
-{
-    type Rejection = ApiResponse<()>;
-
-    async fn from_request(req: Request, state: &S) -> Result<Self, ApiResponse<()>> {
-        let req_id: ReqId = {
-            let req = &req;
-            let id: &ReqId = req.extensions().get().unwrap();
-            id.clone()
-        };
-
-        let axum::Json(payload) = axum::Json::<T>::from_request(req, state).await.map_err(|e| {
-            let err_msg = e.body_text();
-            ApiResponse::Err(AppError::err(ErrType::InvalidBody, e, err_msg), req_id.clone())
-        })?;
-
-        payload.validate().map_err(|e| {
-            let err_msg = format!("Bad Payload: {}", e);
-            ApiResponse::Err(AppError::err(ErrType::ValidationErr, e, err_msg), req_id.clone())
-        })?;
-
-        Ok(Json(payload))
-    }
-}
+#[cfg(feature = "server")]
+pub use response::{ApiResponse, EmptyResponse, Json};
 
 #[derive(Debug)]
 pub enum ErrType {
     Unauthorized,
+    Forbidden,
     BadRequest,
     NotFound,
     ServerError,
@@ -92,6 +32,7 @@ impl Display for ErrType {
             "{}",
             match self {
                 ErrType::Unauthorized => "Unauthorized",
+                ErrType::Forbidden => "Forbidden",
                 ErrType::BadRequest => "BadRequest",
                 ErrType::NotFound => "NotFound",
                 ErrType::ServerError => "ServerError",
@@ -169,70 +110,3 @@ impl AppError {
 // }
 
 pub type AppResult<T> = Result<T, AppError>;
-
-pub enum ApiResponse<T> {
-    Ok(T),
-    Err(AppError, ReqId),
-}
-
-impl<T> ApiResponse<T>
-where
-    T: Serialize,
-{
-    pub fn map_res(res: AppResult<T>, req_id: ReqId) -> Self {
-        match res {
-            Ok(ok) => ApiResponse::Ok(ok),
-            Err(err) => ApiResponse::Err(err, req_id),
-        }
-    }
-}
-
-impl<T> IntoResponse for ApiResponse<T>
-where
-    T: Serialize,
-    axum::Json<T>: IntoResponse,
-{
-    /// Function to map errors into appropriate responses
-    fn into_response(self) -> Response {
-        match self {
-            ApiResponse::Ok(json) => Json(json).into_response(),
-            ApiResponse::Err(err, req_id) => {
-                let id: &str = req_id.as_ref();
-                let _type = err._type;
-                let err_msg = err.err_msg;
-                let message = format!("[{}]: {}", _type, err.message);
-                let at = err.at;
-
-                let status = match _type {
-                    ErrType::InvalidBody => StatusCode::BAD_REQUEST,
-                    ErrType::Unauthorized => StatusCode::UNAUTHORIZED,
-                    ErrType::BadRequest => StatusCode::BAD_REQUEST,
-                    ErrType::NotFound => StatusCode::NOT_FOUND,
-                    ErrType::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
-                    ErrType::DbError => StatusCode::INTERNAL_SERVER_ERROR,
-                    ErrType::VaultError => StatusCode::BAD_REQUEST,
-                    ErrType::ValidationErr => StatusCode::BAD_REQUEST,
-                    ErrType::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
-                };
-
-                match status {
-                    StatusCode::INTERNAL_SERVER_ERROR | StatusCode::FAILED_DEPENDENCY => {
-                        tracing::error!(req_id = id, message = message, at = at, err = err_msg)
-                    }
-                    _ => tracing::warn!(req_id = id, message = message, at = at, err = err_msg),
-                };
-
-                (status, Json(EmptyResponse { status: status.as_u16(), message })).into_response()
-            }
-        }
-    }
-}
-
-impl From<JsonRejection> for ApiResponse<()> {
-    fn from(rejection: JsonRejection) -> Self {
-        ApiResponse::Err(
-            AppError::err(ErrType::InvalidBody, rejection, "Invalid payload"),
-            "".into(),
-        )
-    }
-}
@@ -0,0 +1,95 @@
+//! Export of claws as age files
+//!
+//! Implements encryption to a single X25519 recipient following the age v1
+//! format, see <https://age-encryption.org/v1>. Files are ASCII-armored so
+//! they can be returned as text.
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::backend::{self, Aead, hkdf_sha256};
+use super::key::{KEY_LEN, KeyProvider};
+use super::recipient;
+use crate::AppResult;
+
+const VERSION_LINE: &str = "age-encryption.org/v1";
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const FILE_KEY_LEN: usize = 16;
+const PAYLOAD_NONCE_LEN: usize = 16;
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Encrypts `data` to X25519 `recipient`, returns the armored age file
+pub fn encrypt(
+    provider: &dyn KeyProvider,
+    recipient: &[u8; KEY_LEN],
+    data: &[u8],
+) -> AppResult<String> {
+    let mut file_key = Zeroizing::new([0; FILE_KEY_LEN]);
+    provider.fill_bytes(file_key.as_mut())?;
+
+    // recipient stanza
+    let (ephemeral, share) = recipient::generate(provider)?;
+    let shared = recipient::x25519(&ephemeral, recipient)?;
+    let wrap_key = hkdf_sha256(shared.as_slice(), &[&share[..], recipient].concat(), X25519_LABEL)?;
+    let body = chacha20_poly1305(wrap_key.as_slice(), &[0; 12], file_key.as_slice())?;
+
+    let mut header = format!(
+        "{}\n-> X25519 {}\n{}\n---",
+        VERSION_LINE,
+        encode_nopad(&share),
+        encode_nopad(&body)
+    );
+    let mac_key = hkdf_sha256(file_key.as_slice(), &[], b"header")?;
+    let mac = backend::hmac_sha256(mac_key.as_slice(), header.as_bytes())?;
+    header.push_str(&format!(" {}\n", encode_nopad(&mac)));
+
+    // payload
+    let mut nonce = [0; PAYLOAD_NONCE_LEN];
+    provider.fill_bytes(&mut nonce)?;
+    let payload_key = hkdf_sha256(file_key.as_slice(), &nonce, b"payload")?;
+
+    let mut file = header.into_bytes();
+    file.extend_from_slice(&nonce);
+    // an empty file still has one, empty, final chunk
+    let chunks: Vec<&[u8]> =
+        if data.is_empty() { vec![&[]] } else { data.chunks(CHUNK_LEN).collect() };
+    for (counter, chunk) in chunks.iter().enumerate() {
+        let mut chunk_nonce = [0; 12];
+        chunk_nonce[3..11].copy_from_slice(&(counter as u64).to_be_bytes());
+        chunk_nonce[11] = (counter == chunks.len() - 1) as u8;
+        file.extend(chacha20_poly1305(payload_key.as_slice(), &chunk_nonce, chunk)?);
+    }
+
+    Ok(armor(&file))
+}
+
+/// Returns whether `blob` looks like an age file, armored or not
+pub fn is_age(blob: &[u8]) -> bool {
+    let blob = blob.trim_ascii_start();
+    blob.starts_with(format!("{}\n", VERSION_LINE).as_bytes())
+        || blob.starts_with(ARMOR_BEGIN.as_bytes())
+}
+
+/// Encrypts `data` with ChaCha20-Poly1305, returns `ciphertext | tag`
+fn chacha20_poly1305(key: &[u8], nonce: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    backend::aead_encrypt(Aead::ChaCha20Poly1305, key, nonce, &[], data)
+}
+
+fn encode_nopad(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD_NO_PAD.encode(data)
+}
+
+/// Wraps `file` in age's strict PEM armor
+fn armor(file: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(file);
+    let mut armored = format!("{}\n", ARMOR_BEGIN);
+    for line in encoded.as_bytes().chunks(64) {
+        armored.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        armored.push('\n');
+    }
+    armored.push_str(ARMOR_END);
+    armored.push('\n');
+    armored
+}
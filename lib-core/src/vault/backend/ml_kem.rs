@@ -0,0 +1,81 @@
+//! ML-KEM-768 of FIPS 203, shared by both backends
+//!
+//! Backed by the RustCrypto `ml-kem` crate, as the safe OpenSSL bindings
+//! don't offer ML-KEM encapsulation. Keys are always expanded from their 64
+//! byte seed `d | z`, and the message `m` of encapsulation is taken from the
+//! caller, so that the hybrid recipient suite draws all randomness from its
+//! [`crate::vault::key::KeyProvider`].
+
+use ml_kem::{
+    B32, Ciphertext, EncapsulateDeterministic, Encoded, EncodedSizeUser, KemCore, MlKem768,
+    SharedKey, kem::Decapsulate,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::ML_KEM_768_SEED_LEN;
+use crate::vault::key::KEY_LEN;
+use crate::{AppError, AppResult, ErrType};
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// Returns encapsulation key of the private key expanded from `seed`
+pub fn ml_kem_768_public_key(seed: &[u8; ML_KEM_768_SEED_LEN]) -> AppResult<Vec<u8>> {
+    let (_, public) = expand(seed);
+    Ok(public.as_bytes().to_vec())
+}
+
+/// Encapsulates a shared secret to `public` key using `entropy` as the message
+/// `m` of FIPS 203, returns `(ciphertext, shared secret)`
+pub fn ml_kem_768_encapsulate(
+    public: &[u8],
+    entropy: &[u8; KEY_LEN],
+) -> AppResult<(Vec<u8>, Zeroizing<[u8; KEY_LEN]>)> {
+    let invalid = || AppError::new(ErrType::VaultError, "Invalid ML-KEM-768 public key");
+    let encoded = <&Encoded<EncapsulationKey>>::try_from(public).map_err(|_| invalid())?;
+    let public = EncapsulationKey::from_bytes(encoded);
+    // the modulus check of FIPS 203, decoding reduces coefficients that aren't
+    if public.as_bytes() != *encoded {
+        return Err(invalid());
+    }
+
+    let (ciphertext, shared) = public
+        .encapsulate_deterministic(<&B32>::from(entropy))
+        .map_err(|_| AppError::new(ErrType::VaultError, "Failed to encapsulate ML-KEM-768"))?;
+    Ok((ciphertext.to_vec(), take_secret(shared)))
+}
+
+/// Decapsulates shared secret of `ciphertext` with the private key expanded
+/// from `seed`
+///
+/// A ciphertext not made for the key yields an unrelated secret, not an error.
+pub fn ml_kem_768_decapsulate(
+    seed: &[u8; ML_KEM_768_SEED_LEN],
+    ciphertext: &[u8],
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let ciphertext = <&Ciphertext<MlKem768>>::try_from(ciphertext)
+        .map_err(|_| AppError::new(ErrType::VaultError, "Invalid ML-KEM-768 ciphertext"))?;
+    let (private, _) = expand(seed);
+
+    let shared = private
+        .decapsulate(ciphertext)
+        .map_err(|_| AppError::new(ErrType::VaultError, "Failed to decapsulate ML-KEM-768"))?;
+    Ok(take_secret(shared))
+}
+
+/// Expands `seed` into the key pair, the private key is wiped once dropped
+fn expand(seed: &[u8; ML_KEM_768_SEED_LEN]) -> (DecapsulationKey, EncapsulationKey) {
+    let (d, z) = seed.split_at(KEY_LEN);
+    MlKem768::generate_deterministic(
+        d.try_into().expect("split at KEY_LEN"),
+        z.try_into().expect("split at KEY_LEN"),
+    )
+}
+
+/// Moves `shared` secret into a zeroizing buffer, wiping the original
+fn take_secret(mut shared: SharedKey<MlKem768>) -> Zeroizing<[u8; KEY_LEN]> {
+    let mut secret = Zeroizing::new([0; KEY_LEN]);
+    secret.copy_from_slice(&shared);
+    shared.as_mut_slice().zeroize();
+    secret
+}
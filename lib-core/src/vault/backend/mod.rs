@@ -0,0 +1,49 @@
+//! Cryptographic primitives claws are built on
+//!
+//! Implemented on top of OpenSSL with the `openssl` feature, enabled by
+//! default, or on top of pure-Rust RustCrypto crates with the `rustcrypto`
+//! feature. Both produce the same bytes for the same input, so claws written
+//! by a build with one backend are read by a build with the other. OpenSSL is
+//! used when both features are enabled. ML-KEM-768 is taken from the `ml-kem`
+//! crate in [`ml_kem`] for both backends alike.
+
+#[cfg(not(any(feature = "openssl", feature = "rustcrypto")))]
+compile_error!("either the `openssl` or the `rustcrypto` feature must be enabled");
+
+mod ml_kem;
+#[cfg(feature = "openssl")]
+pub mod openssl;
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
+
+#[cfg(feature = "openssl")]
+pub use self::openssl::*;
+#[cfg(all(feature = "rustcrypto", not(feature = "openssl")))]
+pub use self::rustcrypto::*;
+
+pub use self::ml_kem::{ml_kem_768_decapsulate, ml_kem_768_encapsulate, ml_kem_768_public_key};
+
+/// Length of authentication tags of [`Aead`]s
+pub const TAG_LEN: usize = 16;
+
+/// Length of ML-KEM-768 private key seeds, `d | z` of FIPS 203
+pub const ML_KEM_768_SEED_LEN: usize = 64;
+/// Length of ML-KEM-768 encapsulation keys
+pub const ML_KEM_768_PUBLIC_KEY_LEN: usize = 1184;
+/// Length of ML-KEM-768 ciphertexts
+pub const ML_KEM_768_CIPHERTEXT_LEN: usize = 1088;
+
+/// AEAD with 96-bit nonces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aead {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+/// Hash function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Digest {
+    Sha256,
+    Sha384,
+    Sha512,
+}
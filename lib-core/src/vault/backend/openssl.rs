@@ -0,0 +1,234 @@
+//! Primitives implemented with OpenSSL
+
+use ::openssl::{
+    aes::{AesKey, wrap_key},
+    bn::BigNum,
+    derive::Deriver,
+    hash::{MessageDigest, hash},
+    kdf,
+    md::Md,
+    memcmp,
+    pkey::{Id, PKey},
+    pkey_ctx::PkeyCtx,
+    rsa::{Padding, Rsa},
+    sha,
+    sign::{Signer, Verifier},
+    symm::{self, Cipher, decrypt_aead, encrypt_aead},
+};
+
+use zeroize::Zeroizing;
+
+use super::{Aead, Digest, TAG_LEN};
+use crate::vault::key::KEY_LEN;
+use crate::{AppError, AppResult, ErrType};
+
+fn cipher(aead: Aead) -> Cipher {
+    match aead {
+        Aead::Aes256Gcm => Cipher::aes_256_gcm(),
+        Aead::ChaCha20Poly1305 => Cipher::chacha20_poly1305(),
+    }
+}
+
+/// Encrypts `data` with `aead`, returns `ciphertext | tag`
+pub fn aead_encrypt(
+    aead: Aead,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    data: &[u8],
+) -> AppResult<Vec<u8>> {
+    let mut tag = [0; TAG_LEN];
+    let mut encrypted = encrypt_aead(cipher(aead), key, Some(nonce), aad, data, &mut tag)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to encrypt data"))?;
+    encrypted.extend_from_slice(&tag);
+    Ok(encrypted)
+}
+
+/// Decrypts `ciphertext | tag` with `aead`
+pub fn aead_decrypt(
+    aead: Aead,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    data: &[u8],
+) -> AppResult<Zeroizing<Vec<u8>>> {
+    if data.len() < TAG_LEN {
+        return Err(AppError::new(ErrType::VaultError, "Encrypted data is malformed"));
+    }
+    let (ciphertext, tag) = data.split_at(data.len() - TAG_LEN);
+    decrypt_aead(cipher(aead), key, Some(nonce), aad, ciphertext, tag)
+        .map(Zeroizing::new)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to decrypt data"))
+}
+
+/// Hashes `data` with `digest`
+pub fn digest(digest: Digest, data: &[u8]) -> Vec<u8> {
+    let md = match digest {
+        Digest::Sha256 => MessageDigest::sha256(),
+        Digest::Sha384 => MessageDigest::sha384(),
+        Digest::Sha512 => MessageDigest::sha512(),
+    };
+    hash(md, data).expect("digest of in-memory data").to_vec()
+}
+
+/// Hashes `data` with SHA-1
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    sha::sha1(data)
+}
+
+/// Hashes `data` with SHA-256
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    sha::sha256(data)
+}
+
+/// Computes HMAC-SHA256 of `data` under `key`
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> AppResult<[u8; 32]> {
+    PKey::hmac(key)
+        .and_then(|key| Signer::new(MessageDigest::sha256(), &key)?.sign_oneshot_to_vec(data))
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to compute MAC"))?
+        .try_into()
+        .map_err(|_| AppError::new(ErrType::VaultError, "Failed to compute MAC"))
+}
+
+/// Derives [`KEY_LEN`] bytes from `key` and `salt` bound to `info`, an empty
+/// salt is treated as absent
+pub fn hkdf_sha256(key: &[u8], salt: &[u8], info: &[u8]) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let mut derived = Zeroizing::new([0; KEY_LEN]);
+    PkeyCtx::new_id(Id::HKDF)
+        .and_then(|mut ctx| {
+            ctx.derive_init()?;
+            ctx.set_hkdf_md(Md::sha256())?;
+            ctx.set_hkdf_key(key)?;
+            if !salt.is_empty() {
+                ctx.set_hkdf_salt(salt)?;
+            }
+            ctx.add_hkdf_info(info)?;
+            ctx.derive(Some(derived.as_mut()))
+        })
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to derive key"))?;
+    Ok(derived)
+}
+
+/// Stretches `passphrase` with Argon2id, `memcost` is in KiB
+pub fn argon2id(
+    passphrase: &[u8],
+    salt: &[u8],
+    iter: u32,
+    lanes: u32,
+    memcost: u32,
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    kdf::argon2id(None, passphrase, salt, None, None, iter, lanes, memcost, key.as_mut())
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to stretch passphrase"))?;
+    Ok(key)
+}
+
+/// Returns public key of X25519 `private` key
+pub fn x25519_public_key(private: &[u8; KEY_LEN]) -> AppResult<[u8; KEY_LEN]> {
+    PKey::private_key_from_raw_bytes(private, Id::X25519)
+        .and_then(|k| k.raw_public_key())
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Invalid X25519 private key"))?
+        .try_into()
+        .map_err(|_| AppError::new(ErrType::VaultError, "Invalid X25519 public key"))
+}
+
+/// Returns X25519 shared secret of `private` and `public`, not checked for
+/// low order points
+pub fn x25519(
+    private: &[u8; KEY_LEN],
+    public: &[u8; KEY_LEN],
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let mut shared = Zeroizing::new([0; KEY_LEN]);
+    let len = (|| {
+        let private = PKey::private_key_from_raw_bytes(private, Id::X25519)?;
+        let public = PKey::public_key_from_raw_bytes(public, Id::X25519)?;
+        let mut deriver = Deriver::new(&private)?;
+        deriver.set_peer(&public)?;
+        deriver.derive(shared.as_mut())
+    })()
+    .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to derive shared secret"))?;
+    if len != KEY_LEN {
+        return Err(AppError::new(ErrType::VaultError, "Invalid shared secret"));
+    }
+    Ok(shared)
+}
+
+/// Wraps `data` with `kek` following RFC 3394
+pub fn aes_key_wrap(kek: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    let kek =
+        AesKey::new_encrypt(kek).map_err(|_| AppError::new(ErrType::VaultError, "Invalid KEK"))?;
+    let mut wrapped = vec![0; data.len() + 8];
+    wrap_key(&kek, None, &mut wrapped, data)
+        .map_err(|_| AppError::new(ErrType::VaultError, "Failed to wrap key"))?;
+    Ok(wrapped)
+}
+
+/// Encrypts `data` with AES-256 in CFB128 mode
+pub fn aes_256_cfb_encrypt(key: &[u8], iv: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    symm::encrypt(Cipher::aes_256_cfb128(), key, Some(iv), data)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to encrypt data"))
+}
+
+/// Encrypts `data` to big-endian RSA public key `n`, `e` with PKCS#1 v1.5 padding
+pub fn rsa_encrypt(n: &[u8], e: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    (|| {
+        let rsa = Rsa::from_public_components(BigNum::from_slice(n)?, BigNum::from_slice(e)?)?;
+        let mut encrypted = vec![0; rsa.size() as usize];
+        let len = rsa.public_encrypt(data, &mut encrypted, Padding::PKCS1)?;
+        encrypted.truncate(len);
+        Ok(encrypted)
+    })()
+    .map_err(|e: ::openssl::error::ErrorStack| {
+        AppError::err(ErrType::VaultError, e, "Failed to encrypt with RSA")
+    })
+}
+
+fn rsa_public_key(pem: &[u8]) -> AppResult<Rsa<::openssl::pkey::Public>> {
+    Rsa::public_key_from_pem(pem).map_err(|e| {
+        AppError::err(ErrType::VaultError, e, "Failed to generate RSA from public key")
+    })
+}
+
+/// Returns size in bytes of the modulus of PEM encoded RSA public key
+pub fn rsa_size(pem: &[u8]) -> AppResult<usize> {
+    Ok(rsa_public_key(pem)?.size() as usize)
+}
+
+/// Recovers data encrypted with the private key matching PEM encoded RSA
+/// public key, using PKCS#1 v1.5 padding
+pub fn rsa_public_decrypt(pem: &[u8], data: &[u8]) -> AppResult<Zeroizing<Vec<u8>>> {
+    let rsa = rsa_public_key(pem)?;
+    let mut decrypted = Zeroizing::new(vec![0; rsa.size() as usize]);
+    let len = rsa
+        .public_decrypt(data, &mut decrypted, Padding::PKCS1)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to decrypt with RSA"))?;
+    decrypted.truncate(len);
+    Ok(decrypted)
+}
+
+/// Recovers data signed with the private key matching big-endian RSA public
+/// key `n`, `e`, using PKCS#1 v1.5 padding
+pub fn rsa_recover(n: &[u8], e: &[u8], signature: &[u8]) -> AppResult<Vec<u8>> {
+    (|| {
+        let rsa = Rsa::from_public_components(BigNum::from_slice(n)?, BigNum::from_slice(e)?)?;
+        let mut recovered = vec![0; rsa.size() as usize];
+        let len = rsa.public_decrypt(signature, &mut recovered, Padding::PKCS1)?;
+        recovered.truncate(len);
+        Ok(recovered)
+    })()
+    .map_err(|e: ::openssl::error::ErrorStack| {
+        AppError::err(ErrType::VaultError, e, "Failed to verify with RSA")
+    })
+}
+
+/// Returns whether `signature` of `message` is valid for Ed25519 `public` key
+pub fn ed25519_verify(public: &[u8; KEY_LEN], message: &[u8], signature: &[u8; 64]) -> bool {
+    PKey::public_key_from_raw_bytes(public, Id::ED25519)
+        .and_then(|key| Verifier::new_without_digest(&key)?.verify_oneshot(signature, message))
+        .unwrap_or(false)
+}
+
+/// Compares `a` and `b` in constant time
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && memcmp::eq(a, b)
+}
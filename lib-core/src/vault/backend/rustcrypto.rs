@@ -0,0 +1,238 @@
+//! Primitives implemented with pure-Rust RustCrypto crates
+
+use aes_gcm::{
+    Aes256Gcm,
+    aead::{Aead as _, KeyInit, Payload},
+};
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use argon2::{Algorithm, Argon2, Params, Version};
+use cfb_mode::cipher::{AsyncStreamCipher, KeyIvInit};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rsa::{
+    BigUint, Pkcs1v15Encrypt, RsaPublicKey, hazmat, pkcs8::DecodePublicKey, rand_core::OsRng,
+    traits::PublicKeyParts,
+};
+use sha2::{Digest as _, Sha256, Sha384, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use super::{Aead, Digest};
+use crate::vault::key::KEY_LEN;
+use crate::{AppError, AppResult, ErrType};
+
+/// Length of nonce of all [`Aead`]s
+const NONCE_LEN: usize = 12;
+
+/// Encrypts `data` with `aead`, returns `ciphertext | tag`
+pub fn aead_encrypt(
+    aead: Aead,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    data: &[u8],
+) -> AppResult<Vec<u8>> {
+    if nonce.len() != NONCE_LEN {
+        return Err(AppError::new(ErrType::VaultError, "Nonce is malformed"));
+    }
+    let payload = Payload { msg: data, aad };
+    match aead {
+        Aead::Aes256Gcm => Aes256Gcm::new_from_slice(key).map(|c| c.encrypt(nonce.into(), payload)),
+        Aead::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new_from_slice(key).map(|c| c.encrypt(nonce.into(), payload))
+        }
+    }
+    .map_err(|_| AppError::new(ErrType::VaultError, "Key is malformed"))?
+    .map_err(|_| AppError::new(ErrType::VaultError, "Failed to encrypt data"))
+}
+
+/// Decrypts `ciphertext | tag` with `aead`
+pub fn aead_decrypt(
+    aead: Aead,
+    key: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+    data: &[u8],
+) -> AppResult<Zeroizing<Vec<u8>>> {
+    if nonce.len() != NONCE_LEN {
+        return Err(AppError::new(ErrType::VaultError, "Nonce is malformed"));
+    }
+    let payload = Payload { msg: data, aad };
+    match aead {
+        Aead::Aes256Gcm => Aes256Gcm::new_from_slice(key).map(|c| c.decrypt(nonce.into(), payload)),
+        Aead::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new_from_slice(key).map(|c| c.decrypt(nonce.into(), payload))
+        }
+    }
+    .map_err(|_| AppError::new(ErrType::VaultError, "Key is malformed"))?
+    .map(Zeroizing::new)
+    .map_err(|_| AppError::new(ErrType::VaultError, "Failed to decrypt data"))
+}
+
+/// Hashes `data` with `digest`
+pub fn digest(digest: Digest, data: &[u8]) -> Vec<u8> {
+    match digest {
+        Digest::Sha256 => Sha256::digest(data).to_vec(),
+        Digest::Sha384 => Sha384::digest(data).to_vec(),
+        Digest::Sha512 => Sha512::digest(data).to_vec(),
+    }
+}
+
+/// Hashes `data` with SHA-1
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    ::sha1::Sha1::digest(data).into()
+}
+
+/// Hashes `data` with SHA-256
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Computes HMAC-SHA256 of `data` under `key`
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> AppResult<[u8; 32]> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+        .map_err(|_| AppError::new(ErrType::VaultError, "Failed to compute MAC"))?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().into())
+}
+
+/// Derives [`KEY_LEN`] bytes from `key` and `salt` bound to `info`, an empty
+/// salt is treated as absent
+pub fn hkdf_sha256(key: &[u8], salt: &[u8], info: &[u8]) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let salt = if salt.is_empty() { None } else { Some(salt) };
+    let mut derived = Zeroizing::new([0; KEY_LEN]);
+    Hkdf::<Sha256>::new(salt, key)
+        .expand(info, derived.as_mut())
+        .map_err(|_| AppError::new(ErrType::VaultError, "Failed to derive key"))?;
+    Ok(derived)
+}
+
+/// Stretches `passphrase` with Argon2id, `memcost` is in KiB
+pub fn argon2id(
+    passphrase: &[u8],
+    salt: &[u8],
+    iter: u32,
+    lanes: u32,
+    memcost: u32,
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let stretch_err = |e: argon2::Error| {
+        AppError::new(ErrType::VaultError, format!("Failed to stretch passphrase: {}", e))
+    };
+
+    let params = Params::new(memcost, iter, lanes, Some(KEY_LEN)).map_err(stretch_err)?;
+    let mut key = Zeroizing::new([0; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase, salt, key.as_mut())
+        .map_err(stretch_err)?;
+    Ok(key)
+}
+
+/// Returns public key of X25519 `private` key
+pub fn x25519_public_key(private: &[u8; KEY_LEN]) -> AppResult<[u8; KEY_LEN]> {
+    Ok(x25519_dalek::x25519(*private, x25519_dalek::X25519_BASEPOINT_BYTES))
+}
+
+/// Returns X25519 shared secret of `private` and `public`, not checked for
+/// low order points
+pub fn x25519(
+    private: &[u8; KEY_LEN],
+    public: &[u8; KEY_LEN],
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    Ok(Zeroizing::new(x25519_dalek::x25519(*private, *public)))
+}
+
+/// Wraps `data` with `kek` following RFC 3394
+pub fn aes_key_wrap(kek: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    let invalid = |_| AppError::new(ErrType::VaultError, "Invalid KEK");
+    match kek.len() {
+        16 => KekAes128::try_from(kek).map_err(invalid)?.wrap_vec(data),
+        24 => KekAes192::try_from(kek).map_err(invalid)?.wrap_vec(data),
+        32 => KekAes256::try_from(kek).map_err(invalid)?.wrap_vec(data),
+        _ => return Err(AppError::new(ErrType::VaultError, "Invalid KEK")),
+    }
+    .map_err(|_| AppError::new(ErrType::VaultError, "Failed to wrap key"))
+}
+
+/// Encrypts `data` with AES-256 in CFB128 mode
+pub fn aes_256_cfb_encrypt(key: &[u8], iv: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    let mut encrypted = data.to_vec();
+    cfb_mode::Encryptor::<aes::Aes256>::new_from_slices(key, iv)
+        .map_err(|_| AppError::new(ErrType::VaultError, "Key is malformed"))?
+        .encrypt(&mut encrypted);
+    Ok(encrypted)
+}
+
+/// Encrypts `data` to big-endian RSA public key `n`, `e` with PKCS#1 v1.5 padding
+pub fn rsa_encrypt(n: &[u8], e: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+    let rsa_err = |e| AppError::err(ErrType::VaultError, e, "Failed to encrypt with RSA");
+    RsaPublicKey::new(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e))
+        .and_then(|key| key.encrypt(&mut OsRng, Pkcs1v15Encrypt, data))
+        .map_err(rsa_err)
+}
+
+fn rsa_public_key(pem: &[u8]) -> AppResult<RsaPublicKey> {
+    std::str::from_utf8(pem)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Public key is malformed"))
+        .and_then(|pem| {
+            RsaPublicKey::from_public_key_pem(pem).map_err(|e| {
+                AppError::err(ErrType::VaultError, e, "Failed to generate RSA from public key")
+            })
+        })
+}
+
+/// Returns size in bytes of the modulus of PEM encoded RSA public key
+pub fn rsa_size(pem: &[u8]) -> AppResult<usize> {
+    Ok(rsa_public_key(pem)?.size())
+}
+
+/// Recovers data encrypted with the private key matching PEM encoded RSA
+/// public key, using PKCS#1 v1.5 padding
+pub fn rsa_public_decrypt(pem: &[u8], data: &[u8]) -> AppResult<Zeroizing<Vec<u8>>> {
+    rsa_unpad(&rsa_public_key(pem)?, data, "Failed to decrypt with RSA")
+}
+
+/// Recovers data signed with the private key matching big-endian RSA public
+/// key `n`, `e`, using PKCS#1 v1.5 padding
+pub fn rsa_recover(n: &[u8], e: &[u8], signature: &[u8]) -> AppResult<Vec<u8>> {
+    let key = RsaPublicKey::new(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e))
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to verify with RSA"))?;
+    let signature = [&vec![0; key.size().saturating_sub(signature.len())][..], signature].concat();
+    Ok(rsa_unpad(&key, &signature, "Failed to verify with RSA")?.to_vec())
+}
+
+/// Applies `key` to `data` and strips the PKCS#1 v1.5 type 1 padding
+fn rsa_unpad(key: &RsaPublicKey, data: &[u8], message: &str) -> AppResult<Zeroizing<Vec<u8>>> {
+    let malformed = || AppError::new(ErrType::VaultError, message);
+
+    let c = BigUint::from_bytes_be(data);
+    if data.len() != key.size() || &c >= key.n() {
+        return Err(malformed());
+    }
+    let m = Zeroizing::new(hazmat::rsa_encrypt(key, &c).map_err(|_| malformed())?.to_bytes_be());
+
+    // `00 01 ff.. 00 data` with the leading zero dropped by the conversion
+    let mut em = Zeroizing::new(vec![0; key.size()]);
+    em[key.size() - m.len()..].copy_from_slice(&m);
+    let padding = em.get(2..).map(|p| p.iter().take_while(|b| **b == 0xff).count());
+    match padding {
+        Some(len) if em[..2] == [0, 1] && len >= 8 && em.get(2 + len) == Some(&0) => {
+            Ok(Zeroizing::new(em[3 + len..].to_vec()))
+        }
+        _ => Err(malformed()),
+    }
+}
+
+/// Returns whether `signature` of `message` is valid for Ed25519 `public` key
+pub fn ed25519_verify(public: &[u8; KEY_LEN], message: &[u8], signature: &[u8; 64]) -> bool {
+    ed25519_dalek::VerifyingKey::from_bytes(public)
+        .and_then(|key| {
+            key.verify_strict(message, &ed25519_dalek::Signature::from_bytes(signature))
+        })
+        .is_ok()
+}
+
+/// Compares `a` and `b` in constant time
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
@@ -0,0 +1,199 @@
+//! Portable `.claw` files, claws sealed and opened without a server
+//!
+//! ```text
+//! -----BEGIN CLAW FILE-----
+//! Version: 5
+//! Suite: aes-256-gcm
+//! Wrapped-Key: <wrapped content key, base64>
+//! Kdf: <argon2 parameters, if passphrase protected>
+//! Filename: <name, optional>
+//! Mime: <media type, optional>
+//! Expires: <RFC 3339 time, optional>
+//!
+//! <encrypted data, base64 wrapped at 64 columns>
+//! -----END CLAW FILE-----
+//! ```
+//!
+//! The claw key is not part of the file and has to be handed over apart from
+//! it. Version, suite and key derivation are bound to the ciphertext, the
+//! other headers are not: the expiry is a hint that a holder of the file may
+//! ignore, it can't make the file unreadable.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use zeroize::Zeroizing;
+
+use super::{ClawVersion, EData, passphrase::Argon2Params, suite::Suite};
+use crate::{AppError, AppResult, ErrType};
+
+const BEGIN: &str = "-----BEGIN CLAW FILE-----";
+const END: &str = "-----END CLAW FILE-----";
+
+/// Claw stored in a `.claw` file
+pub struct ClawFile {
+    pub version: ClawVersion,
+    pub suite: Suite,
+    /// Encrypted data, base64
+    pub encrypted: String,
+    /// Wrapped content key, base64
+    pub e_pem: String,
+    pub kdf: Option<String>,
+    pub filename: Option<String>,
+    pub mime: Option<String>,
+    /// Time after which the claw should no longer be opened
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ClawFile {
+    /// Builds file of freshly encrypted `edata`, returns it along with the
+    /// claw key, which is not stored in the file
+    pub fn new(
+        EData { version, suite, key, encrypted, e_pem, kdf, .. }: EData,
+        filename: Option<String>,
+        mime: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> AppResult<(Self, Zeroizing<String>)> {
+        check_version(version)?;
+        let file = ClawFile { version, suite, encrypted, e_pem, kdf, filename, mime, expires_at };
+        Ok((file, key))
+    }
+
+    /// Returns [`EData`] to decrypt the file with claw `key`
+    pub fn into_edata(self, key: Zeroizing<String>) -> EData {
+        EData {
+            version: self.version,
+            suite: self.suite,
+            hash: None,
+            key,
+            encrypted: self.encrypted,
+            e_pem: self.e_pem,
+            kdf: self.kdf,
+        }
+    }
+
+    /// Whether the expiry hint of the file has passed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    /// Checks whether `blob` looks like a `.claw` file
+    pub fn is_claw_file(blob: &[u8]) -> bool {
+        blob.trim_ascii_start().starts_with(BEGIN.as_bytes())
+    }
+}
+
+impl Display for ClawFile {
+    /// Writes the armored file
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", BEGIN)?;
+        writeln!(f, "Version: {}", self.version as i16)?;
+        writeln!(f, "Suite: {}", self.suite)?;
+        writeln!(f, "Wrapped-Key: {}", self.e_pem)?;
+        let optional = [
+            ("Kdf", self.kdf.clone()),
+            ("Filename", self.filename.clone()),
+            ("Mime", self.mime.clone()),
+            ("Expires", self.expires_at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                writeln!(f, "{}: {}", name, value)?;
+            }
+        }
+
+        writeln!(f)?;
+        for line in self.encrypted.as_bytes().chunks(64) {
+            writeln!(f, "{}", std::str::from_utf8(line).expect("base64 is ASCII"))?;
+        }
+        writeln!(f, "{}", END)
+    }
+}
+
+impl FromStr for ClawFile {
+    type Err = AppError;
+
+    /// Parses an armored file
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = |reason: &str| {
+            AppError::new(ErrType::BadRequest, format!("Claw file is malformed: {}", reason))
+        };
+
+        let mut lines = s.lines().map(str::trim).skip_while(|l| l.is_empty());
+        if lines.next() != Some(BEGIN) {
+            return Err(malformed("missing begin line"));
+        }
+
+        let (mut version, mut suite, mut e_pem) = (None, None, None);
+        let (mut kdf, mut filename, mut mime, mut expires_at) = (None, None, None, None);
+        for line in lines.by_ref().take_while(|l| !l.is_empty()) {
+            let (name, value) = line.split_once(':').ok_or_else(|| malformed("invalid header"))?;
+            let value = value.trim().to_string();
+            match name.trim() {
+                "Version" => {
+                    let value = value.parse::<i16>().map_err(|_| malformed("invalid version"))?;
+                    version = Some(ClawVersion::try_from(value)?);
+                }
+                "Suite" => suite = Some(value.parse()?),
+                "Wrapped-Key" => e_pem = Some(value),
+                // parsed up front, so that excessive costs are refused before
+                // anyone stretches a passphrase with them
+                "Kdf" => {
+                    value.parse::<Argon2Params>()?;
+                    kdf = Some(value);
+                }
+                "Filename" => filename = Some(value),
+                "Mime" => mime = Some(value),
+                "Expires" => {
+                    let at = DateTime::parse_from_rfc3339(&value)
+                        .map_err(|_| malformed("invalid expiry"))?;
+                    expires_at = Some(at.with_timezone(&Utc));
+                }
+                // unknown headers are skipped, so that later additions stay readable
+                _ => {}
+            }
+        }
+
+        let mut encrypted = String::new();
+        let mut ended = false;
+        for line in lines {
+            if line == END {
+                ended = true;
+                break;
+            }
+            encrypted.push_str(line);
+        }
+        if !ended {
+            return Err(malformed("missing end line"));
+        }
+        base64::engine::general_purpose::STANDARD
+            .decode(&encrypted)
+            .map_err(|_| malformed("invalid data"))?;
+
+        let version = version.ok_or_else(|| malformed("missing version"))?;
+        check_version(version)?;
+        Ok(ClawFile {
+            version,
+            suite: suite.ok_or_else(|| malformed("missing suite"))?,
+            encrypted,
+            e_pem: e_pem.ok_or_else(|| malformed("missing wrapped key"))?,
+            kdf,
+            filename,
+            mime,
+            expires_at,
+        })
+    }
+}
+
+/// Checks that claws of `version` are self-contained and opened by the claw key alone
+fn check_version(version: ClawVersion) -> AppResult<()> {
+    match version {
+        ClawVersion::V3 | ClawVersion::V5 => Ok(()),
+        _ => Err(AppError::new(
+            ErrType::BadRequest,
+            format!("Claws of version {} can't be stored in a claw file", version as i16),
+        )),
+    }
+}
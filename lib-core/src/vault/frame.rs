@@ -0,0 +1,154 @@
+//! Framing of plaintext inside the AEAD since [`super::ClawVersion::V5`]
+//!
+//! ```text
+//! flags (1) | original length (8, if compressed) | body | padding (if padded)
+//! ```
+//!
+//! Flags are encrypted along with the data, so they can't be tampered with.
+//! Compression reveals the compressibility of the data through the length of
+//! the ciphertext, it must not be enabled where an attacker controls part of
+//! the data.
+//!
+//! Padding follows ISO/IEC 7816-4, a `0x80` byte followed by zeros, so it is
+//! stripped unambiguously whatever the data ends with.
+
+use std::str::FromStr;
+
+use zeroize::Zeroizing;
+
+use crate::{AppError, AppResult, ErrType};
+
+/// Body is compressed with zstd
+const FLAG_ZSTD: u8 = 0x01;
+/// Frame is padded to a [`Padding`] bucket
+const FLAG_PADDED: u8 = 0x02;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Size buckets framed data is padded to, hiding the length of the plaintext
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Padding {
+    /// Data is not padded
+    #[default]
+    None,
+    /// Padded to the next power of two
+    PowerOfTwo,
+    /// Padded to the next multiple of the given number of bytes
+    Step(usize),
+}
+
+impl Padding {
+    /// Returns size of the bucket `len` bytes fall into
+    fn bucket(&self, len: usize) -> AppResult<usize> {
+        match self {
+            Padding::None => Some(len),
+            Padding::PowerOfTwo => len.checked_next_power_of_two(),
+            Padding::Step(step) => len.div_ceil(*step).checked_mul(*step),
+        }
+        .ok_or_else(|| AppError::new(ErrType::VaultError, "Data is too large to pad"))
+    }
+}
+
+impl FromStr for Padding {
+    type Err = AppError;
+
+    /// Parses `none`, `pow2` or a step in bytes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "none" => Ok(Padding::None),
+            "pow2" => Ok(Padding::PowerOfTwo),
+            step => match step.parse() {
+                Ok(step) if step > 0 => Ok(Padding::Step(step)),
+                _ => Err(AppError::new(ErrType::VaultError, format!("Invalid padding: {}", s))),
+            },
+        }
+    }
+}
+
+/// Frames `data`, compressing it if `compress` is set and it saves space, and
+/// pads it according to `padding`
+pub(super) fn encode(
+    data: &[u8],
+    compress: bool,
+    padding: Padding,
+) -> AppResult<Zeroizing<Vec<u8>>> {
+    let compressed = if compress { compressed(data)? } else { None };
+    let (flags, body) = match &compressed {
+        Some(body) => (FLAG_ZSTD, body.as_slice()),
+        None => (0, data),
+    };
+
+    // sized up front, so that growing the frame leaves no copies of it behind
+    let len = match padding {
+        Padding::None => body.len() + 1,
+        _ => padding.bucket(body.len() + 2)?,
+    };
+    let mut framed = Zeroizing::new(Vec::with_capacity(len));
+    framed.push(flags);
+    framed.extend_from_slice(body);
+
+    if padding != Padding::None {
+        framed[0] |= FLAG_PADDED;
+        framed.push(0x80);
+        framed.resize(len, 0);
+    }
+    Ok(framed)
+}
+
+/// Returns compressed body of `data` unless compression does not save space
+fn compressed(data: &[u8]) -> AppResult<Option<Zeroizing<Vec<u8>>>> {
+    let compressed = zstd::bulk::compress(data, ZSTD_LEVEL)
+        .map(Zeroizing::new)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to compress data"))?;
+    if compressed.len() + 8 >= data.len() {
+        return Ok(None);
+    }
+
+    let mut body = Zeroizing::new(Vec::with_capacity(compressed.len() + 8));
+    body.extend_from_slice(&(data.len() as u64).to_be_bytes());
+    body.extend_from_slice(&compressed);
+    Ok(Some(body))
+}
+
+/// Returns data of `framed`, decompressing it up to `max_size` bytes
+pub(super) fn decode(
+    mut framed: Zeroizing<Vec<u8>>,
+    max_size: usize,
+) -> AppResult<Zeroizing<Vec<u8>>> {
+    let malformed = || AppError::new(ErrType::VaultError, "Framed data is malformed");
+
+    let flags = *framed.first().ok_or_else(malformed)?;
+    if flags & !(FLAG_ZSTD | FLAG_PADDED) != 0 {
+        return Err(AppError::new(ErrType::VaultError, format!("Unsupported flags: {}", flags)));
+    }
+
+    if flags & FLAG_PADDED != 0 {
+        let end = framed.iter().rposition(|b| *b != 0).ok_or_else(malformed)?;
+        if end == 0 || framed[end] != 0x80 {
+            return Err(malformed());
+        }
+        framed.truncate(end);
+    }
+
+    if flags & FLAG_ZSTD == 0 {
+        framed.remove(0);
+        return Ok(framed);
+    }
+
+    let len = framed.get(1..9).ok_or_else(malformed)?;
+    let len = u64::from_be_bytes(len.try_into().expect("8 bytes"));
+    if len > max_size as u64 {
+        return Err(AppError::new(
+            ErrType::BadRequest,
+            format!("Decompressed data exceeds maximum size of {} bytes", max_size),
+        ));
+    }
+
+    // capacity bounds the output even if the recorded length lies
+    let data = zstd::bulk::decompress(&framed[9..], len as usize)
+        .map(Zeroizing::new)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to decompress data"))?;
+    if data.len() as u64 != len {
+        return Err(malformed());
+    }
+    Ok(data)
+}
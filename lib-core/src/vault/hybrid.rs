@@ -0,0 +1,164 @@
+//! Claw keys sealed to a recipient's hybrid X25519 + ML-KEM-768 public key
+//!
+//! Works like [`super::recipient`], except that the wrap key is derived from
+//! both an X25519 and an ML-KEM-768 shared secret, so the claw key stays
+//! secret as long as either of them holds, including against an attacker
+//! recording stanzas today to break X25519 with a quantum computer later.
+//! Keys are the base64 encoding of:
+//!
+//! ```text
+//! public:  X25519 public key (32) | ML-KEM-768 encapsulation key (1184)
+//! private: X25519 private key (32) | ML-KEM-768 seed d | z (64)
+//! ```
+//!
+//! A stanza is the base64 encoding of:
+//!
+//! ```text
+//! ephemeral public key (32) | ML-KEM-768 ciphertext (1088) | nonce (12) | ciphertext | tag (16)
+//! ```
+//!
+//! The combiner follows X-Wing with HKDF-SHA256 in place of SHA3-256: the wrap
+//! key is derived from the ML-KEM and the X25519 shared secret, the ephemeral
+//! and the recipient X25519 public key.
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::{
+    backend::{
+        self, Aead, ML_KEM_768_CIPHERTEXT_LEN, ML_KEM_768_PUBLIC_KEY_LEN, ML_KEM_768_SEED_LEN,
+        TAG_LEN,
+    },
+    key::{KEY_LEN, KeyProvider, KeyPurpose},
+    recipient::{self, NONCE_LEN},
+};
+use crate::{AppError, AppResult, ErrType};
+
+/// Length of hybrid public keys
+pub const PUBLIC_KEY_LEN: usize = KEY_LEN + ML_KEM_768_PUBLIC_KEY_LEN;
+/// Length of hybrid private keys
+pub const PRIVATE_KEY_LEN: usize = KEY_LEN + ML_KEM_768_SEED_LEN;
+
+/// Generates a new key pair, returns `(private key, public key)`
+pub fn generate(
+    provider: &dyn KeyProvider,
+) -> AppResult<(Zeroizing<[u8; PRIVATE_KEY_LEN]>, [u8; PUBLIC_KEY_LEN])> {
+    let mut private = Zeroizing::new([0; PRIVATE_KEY_LEN]);
+    provider.fill_bytes(private.as_mut())?;
+    let public = public_key(&private)?;
+    Ok((private, public))
+}
+
+/// Returns public key of hybrid `private` key
+pub fn public_key(private: &[u8; PRIVATE_KEY_LEN]) -> AppResult<[u8; PUBLIC_KEY_LEN]> {
+    let (x25519, seed) = split_private(private);
+    let mut public = [0; PUBLIC_KEY_LEN];
+    public[..KEY_LEN].copy_from_slice(&backend::x25519_public_key(x25519)?);
+    public[KEY_LEN..].copy_from_slice(&backend::ml_kem_768_public_key(seed)?);
+    Ok(public)
+}
+
+/// Encodes `key` as base64, the canonical form of stored keys
+pub fn encode_key(key: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key)
+}
+
+/// Parses a public key given as base64
+pub fn parse_public_key(key: &str) -> AppResult<[u8; PUBLIC_KEY_LEN]> {
+    let key = decode_key(key)?;
+    key.as_slice().try_into().map_err(|_| malformed())
+}
+
+/// Parses a private key given as base64
+pub fn parse_private_key(key: &str) -> AppResult<Zeroizing<[u8; PRIVATE_KEY_LEN]>> {
+    let key = decode_key(key)?;
+    key.as_slice().try_into().map(Zeroizing::new).map_err(|_| malformed())
+}
+
+/// Seals claw `key` to `recipient` public key, returns base64 stanza
+pub fn seal(
+    provider: &dyn KeyProvider,
+    recipient: &[u8; PUBLIC_KEY_LEN],
+    key: &[u8],
+) -> AppResult<String> {
+    let (recipient_x25519, recipient_ml_kem) = recipient.split_at(KEY_LEN);
+    let recipient_x25519: &[u8; KEY_LEN] = recipient_x25519.try_into().expect("split at KEY_LEN");
+
+    let (ephemeral, ephemeral_public) = recipient::generate(provider)?;
+    let shared_x25519 = recipient::x25519(&ephemeral, recipient_x25519)?;
+    let entropy = provider.generate_key()?;
+    let (ciphertext, shared_ml_kem) = backend::ml_kem_768_encapsulate(recipient_ml_kem, &entropy)?;
+    let wrap_key =
+        wrap_key(provider, &shared_ml_kem, &shared_x25519, &ephemeral_public, recipient_x25519)?;
+
+    let mut nonce = [0; NONCE_LEN];
+    provider.fill_bytes(&mut nonce)?;
+
+    let encrypted = backend::aead_encrypt(Aead::Aes256Gcm, wrap_key.as_slice(), &nonce, &[], key)?;
+
+    let stanza = [&ephemeral_public[..], &ciphertext, &nonce, &encrypted].concat();
+    Ok(base64::engine::general_purpose::STANDARD.encode(stanza))
+}
+
+/// Opens `stanza` with the recipient's `private` key, returns the claw key
+pub fn open(
+    provider: &dyn KeyProvider,
+    private: &[u8; PRIVATE_KEY_LEN],
+    stanza: &str,
+) -> AppResult<Zeroizing<Vec<u8>>> {
+    let stanza = base64::engine::general_purpose::STANDARD
+        .decode(stanza)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Error decoding base64"))?;
+    if stanza.len() < KEY_LEN + ML_KEM_768_CIPHERTEXT_LEN + NONCE_LEN + TAG_LEN {
+        return Err(AppError::new(ErrType::VaultError, "Stanza is malformed"));
+    }
+
+    let (ephemeral_public, rest) = stanza.split_at(KEY_LEN);
+    let (ciphertext, rest) = rest.split_at(ML_KEM_768_CIPHERTEXT_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+
+    let ephemeral_public: &[u8; KEY_LEN] = ephemeral_public.try_into().expect("split at KEY_LEN");
+    let (x25519, seed) = split_private(private);
+    let shared_x25519 = recipient::x25519(x25519, ephemeral_public)?;
+    let shared_ml_kem = backend::ml_kem_768_decapsulate(seed, ciphertext)?;
+    let wrap_key = wrap_key(
+        provider,
+        &shared_ml_kem,
+        &shared_x25519,
+        ephemeral_public,
+        &backend::x25519_public_key(x25519)?,
+    )?;
+
+    backend::aead_decrypt(Aead::Aes256Gcm, wrap_key.as_slice(), nonce, &[], encrypted)
+        .map_err(|_| AppError::new(ErrType::Unauthorized, "Stanza is not sealed to this key"))
+}
+
+/// Derives wrap key from both shared secrets, bound to the X25519 public keys
+fn wrap_key(
+    provider: &dyn KeyProvider,
+    shared_ml_kem: &[u8; KEY_LEN],
+    shared_x25519: &[u8; KEY_LEN],
+    ephemeral_public: &[u8; KEY_LEN],
+    recipient: &[u8; KEY_LEN],
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let ikm =
+        Zeroizing::new([&shared_ml_kem[..], shared_x25519, ephemeral_public, recipient].concat());
+    provider.derive_key(&ikm, KeyPurpose::HybridRecipient)
+}
+
+/// Splits `private` into its X25519 private key and ML-KEM-768 seed
+fn split_private(private: &[u8; PRIVATE_KEY_LEN]) -> (&[u8; KEY_LEN], &[u8; ML_KEM_768_SEED_LEN]) {
+    let (x25519, seed) = private.split_at(KEY_LEN);
+    (x25519.try_into().expect("split at KEY_LEN"), seed.try_into().expect("rest is the seed"))
+}
+
+fn decode_key(key: &str) -> AppResult<Zeroizing<Vec<u8>>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(key.trim())
+        .map(Zeroizing::new)
+        .map_err(|_| malformed())
+}
+
+fn malformed() -> AppError {
+    AppError::new(ErrType::BadRequest, "Key is malformed")
+}
@@ -0,0 +1,137 @@
+//! Server-side key-encryption keys
+//!
+//! The stored key material of every claw is wrapped with AES-256-GCM under a
+//! key-encryption key (KEK) the server holds outside of the database, so that
+//! a database dump alone is not enough to decrypt claws. KEKs are versioned,
+//! the version a claw was wrapped with is stored alongside it and older
+//! versions stay usable for unwrapping until their claws are re-wrapped.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::{
+    backend::{self, Aead, TAG_LEN},
+    key::{KEY_LEN, KeyProvider},
+};
+use crate::{AppError, AppResult, ErrType, config::Config};
+
+const NONCE_LEN: usize = 12;
+/// Associated data binding wrapped material to its purpose
+const AAD: &[u8] = b"claw-vault/kek";
+
+/// Versioned set of key-encryption keys
+pub struct Keks {
+    active: Option<i16>,
+    keys: BTreeMap<i16, Zeroizing<[u8; KEY_LEN]>>,
+}
+
+impl Keks {
+    /// Creates a set of `keys` wrapping new material with version `active`
+    pub fn new(active: Option<i16>, keys: BTreeMap<i16, [u8; KEY_LEN]>) -> AppResult<Self> {
+        if let Some(active) = active.filter(|v| !keys.contains_key(v)) {
+            return Err(AppError::new(
+                ErrType::VaultError,
+                format!("Active KEK version {} is not configured", active),
+            ));
+        }
+        let keys = keys.into_iter().map(|(version, key)| (version, Zeroizing::new(key))).collect();
+        Ok(Keks { active, keys })
+    }
+
+    /// Loads KEKs from [`Config::get_keks`] and [`Config::get_active_kek`]
+    ///
+    /// The highest version is active unless configured otherwise
+    pub fn from_config() -> AppResult<Self> {
+        let mut keys = BTreeMap::new();
+        for pair in Config::get_keks().unwrap_or_default().split_terminator(',') {
+            let (version, key) = pair
+                .trim()
+                .split_once(':')
+                .and_then(|(v, k)| Some((v.parse::<i16>().ok()?, k)))
+                .ok_or_else(|| {
+                    AppError::new(ErrType::VaultError, "KEK must be given as `<version>:<key>`")
+                })?;
+            let key = base64::engine::general_purpose::STANDARD
+                .decode(key)
+                .map(Zeroizing::new)
+                .map_err(|e| AppError::err(ErrType::VaultError, e, "KEK is not valid base64"))?;
+            let key = key[..].try_into().map_err(|_| {
+                AppError::new(ErrType::VaultError, format!("KEK must be {} bytes", KEY_LEN))
+            })?;
+            keys.insert(version, key);
+        }
+
+        let active = Config::get_active_kek().or_else(|| keys.keys().next_back().copied());
+        Keks::new(active, keys)
+    }
+
+    /// Returns version of the KEK wrapping new material, if any is configured
+    pub fn active(&self) -> Option<i16> {
+        self.active
+    }
+
+    /// Wraps `material` with the active KEK
+    ///
+    /// Returns the KEK version and base64 `nonce | ciphertext | tag`, `material`
+    /// is returned as is when no KEK is configured
+    pub fn wrap(
+        &self,
+        provider: &dyn KeyProvider,
+        material: String,
+    ) -> AppResult<(Option<i16>, String)> {
+        let Some(active) = self.active else {
+            return Ok((None, material));
+        };
+
+        let mut nonce = [0; NONCE_LEN];
+        provider.fill_bytes(&mut nonce)?;
+
+        let encrypted = backend::aead_encrypt(
+            Aead::Aes256Gcm,
+            self.keys[&active].as_slice(),
+            &nonce,
+            AAD,
+            material.as_bytes(),
+        )?;
+
+        let wrapped = [&nonce[..], &encrypted].concat();
+        Ok((Some(active), base64::engine::general_purpose::STANDARD.encode(wrapped)))
+    }
+
+    /// Unwraps `material` wrapped with KEK `version`, unwrapped material is
+    /// returned as is
+    pub fn unwrap(&self, version: Option<i16>, material: String) -> AppResult<String> {
+        let Some(version) = version else {
+            return Ok(material);
+        };
+        let key = self.keys.get(&version).ok_or_else(|| {
+            AppError::new(ErrType::VaultError, format!("KEK version {} is not configured", version))
+        })?;
+
+        let wrapped = base64::engine::general_purpose::STANDARD
+            .decode(material)
+            .map_err(|e| AppError::err(ErrType::VaultError, e, "Error decoding base64"))?;
+        if wrapped.len() < NONCE_LEN + TAG_LEN {
+            return Err(AppError::new(ErrType::VaultError, "KEK wrapped material is malformed"));
+        }
+        let (nonce, encrypted) = wrapped.split_at(NONCE_LEN);
+
+        let material =
+            backend::aead_decrypt(Aead::Aes256Gcm, key.as_slice(), nonce, AAD, encrypted)?;
+        std::str::from_utf8(&material)
+            .map(String::from)
+            .map_err(|e| AppError::err(ErrType::VaultError, e, "KEK wrapped material is malformed"))
+    }
+
+    /// Re-wraps `material` wrapped with KEK `version` under the active KEK
+    pub fn rewrap(
+        &self,
+        provider: &dyn KeyProvider,
+        version: Option<i16>,
+        material: String,
+    ) -> AppResult<(Option<i16>, String)> {
+        self.wrap(provider, self.unwrap(version, material)?)
+    }
+}
@@ -0,0 +1,65 @@
+use rand::{TryRngCore, rngs::OsRng};
+use zeroize::Zeroizing;
+
+use super::backend::hkdf_sha256;
+use crate::{AppError, AppResult, ErrType};
+
+/// Length of keys handed out and derived by a [`KeyProvider`]
+pub const KEY_LEN: usize = 32;
+
+/// Purpose a subkey is derived for
+///
+/// Each purpose maps to a distinct HKDF info label so that subkeys
+/// derived from the same claw key never collide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyPurpose {
+    /// Wraps the content key of a claw
+    Wrap,
+    /// Seals the claw key to a recipient, see [`super::recipient`]
+    Recipient,
+    /// Seals the claw key to a hybrid recipient, see [`super::hybrid`]
+    HybridRecipient,
+}
+
+impl KeyPurpose {
+    /// Returns HKDF info label for the purpose
+    fn label(&self) -> &'static [u8] {
+        match self {
+            KeyPurpose::Wrap => b"claw-vault/v3/wrap",
+            KeyPurpose::Recipient => b"claw-vault/recipient",
+            KeyPurpose::HybridRecipient => b"claw-vault/recipient/x25519-mlkem768",
+        }
+    }
+}
+
+/// Source of key material for claws
+///
+/// [`OsKeyProvider`] is used by default, tests can inject a deterministic
+/// provider to get reproducible claws.
+pub trait KeyProvider {
+    /// Fills `buf` with random bytes
+    fn fill_bytes(&self, buf: &mut [u8]) -> AppResult<()>;
+
+    /// Generates a new random key
+    fn generate_key(&self) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        self.fill_bytes(key.as_mut())?;
+        Ok(key)
+    }
+
+    /// Derives a subkey of `key` for `purpose` using HKDF-SHA256
+    fn derive_key(&self, key: &[u8], purpose: KeyPurpose) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+        hkdf_sha256(key, &[], purpose.label())
+    }
+}
+
+/// [`KeyProvider`] drawing from the OS CSPRNG
+pub struct OsKeyProvider;
+
+impl KeyProvider for OsKeyProvider {
+    fn fill_bytes(&self, buf: &mut [u8]) -> AppResult<()> {
+        OsRng
+            .try_fill_bytes(buf)
+            .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to generate random bytes"))
+    }
+}
@@ -0,0 +1,551 @@
+use std::marker::PhantomData;
+
+use base64::Engine;
+use key::{KeyProvider, KeyPurpose};
+use passphrase::Argon2Params;
+use stream::{StreamDecryptor, StreamEncryptor};
+use suite::{CipherSuite, Suite};
+use xor_cryptor::XORCryptor;
+use zeroize::Zeroizing;
+
+use crate::{AppError, AppResult, ErrType, config::Config};
+
+pub mod age;
+pub mod backend;
+pub mod file;
+pub mod frame;
+pub mod hybrid;
+pub mod kek;
+pub mod key;
+pub mod passphrase;
+pub mod pgp;
+pub mod recipient;
+pub mod sealed;
+pub mod shamir;
+pub mod stream;
+pub mod suite;
+
+pub trait ClawType {}
+
+pub struct Init;
+pub struct EncryptClaw;
+pub struct StreamClaw;
+pub struct DecryptClaw;
+pub struct ChecksumClaw;
+
+impl ClawType for Init {}
+impl ClawType for EncryptClaw {}
+impl ClawType for StreamClaw {}
+impl ClawType for DecryptClaw {}
+impl ClawType for ChecksumClaw {}
+
+/// Length of authentication tag of all [`Suite`]s
+const TAG_LEN: usize = 16;
+
+/// Envelope version naming the scheme a claw was encrypted with
+///
+/// Stored alongside every claw so that claws encrypted with an older
+/// scheme remain decryptable after the scheme changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClawVersion {
+    /// Payload sealed by the client, opaque to the server
+    ///
+    /// See [`sealed`]
+    Sealed = 0,
+    /// Data encrypted with RSA private key, XRC encrypted pem
+    V1 = 1,
+    /// Data encrypted with AES-256-GCM, content key wrapped with RSA private key,
+    /// XRC encrypted pem
+    V2 = 2,
+    /// Data encrypted with the claw's [`Suite`], content key wrapped with the
+    /// same suite under a subkey derived from a random claw key
+    V3 = 3,
+    /// Keys as in [`ClawVersion::V3`], data encrypted in chunks stored apart from the claw
+    ///
+    /// See [`stream`]
+    V4 = 4,
+    /// Keys as in [`ClawVersion::V3`], plaintext framed and optionally compressed
+    /// before encryption
+    ///
+    /// See [`frame`]
+    V5 = 5,
+}
+
+impl ClawVersion {
+    /// Version used for newly encrypted claws
+    pub const CURRENT: ClawVersion = ClawVersion::V5;
+
+    /// Returns associated data binding ciphertexts to the version
+    fn aad(&self) -> [u8; 1] {
+        [*self as u8]
+    }
+}
+
+impl From<ClawVersion> for i16 {
+    fn from(version: ClawVersion) -> Self {
+        version as i16
+    }
+}
+
+impl TryFrom<i16> for ClawVersion {
+    type Error = AppError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ClawVersion::Sealed),
+            1 => Ok(ClawVersion::V1),
+            2 => Ok(ClawVersion::V2),
+            3 => Ok(ClawVersion::V3),
+            4 => Ok(ClawVersion::V4),
+            5 => Ok(ClawVersion::V5),
+            _ => Err(AppError::new(
+                ErrType::VaultError,
+                format!("Unsupported claw version: {}", value),
+            )),
+        }
+    }
+}
+
+pub struct Vault<T = Init> {
+    version: ClawVersion,
+    suite: Suite,
+    hash: Option<String>,
+    key: Zeroizing<String>,
+    data: Zeroizing<Vec<u8>>,
+    e_pem: String,
+    kdf: Option<String>,
+    passphrase: Option<Zeroizing<String>>,
+    /// Compress data before encryption
+    compress: bool,
+    /// Size buckets data is padded to before encryption
+    padding: frame::Padding,
+    /// Maximum size of decompressed data
+    max_size: usize,
+    _marker: PhantomData<T>,
+}
+
+pub struct EData {
+    pub version: ClawVersion,
+    /// [`Suite`] data and content key are encrypted with since [`ClawVersion::V3`]
+    pub suite: Suite,
+    /// SHA-256 of the data, kept for [`ClawVersion::V1`] claws only
+    ///
+    /// Later versions are authenticated by their AEAD tags
+    pub hash: Option<String>,
+    pub key: Zeroizing<String>,
+    pub encrypted: String,
+    /// Encrypted pem, or wrapped content key since [`ClawVersion::V3`]
+    pub e_pem: String,
+    /// [`Argon2Params`] of the passphrase protecting the claw, if any
+    pub kdf: Option<String>,
+}
+
+impl<T> Vault<T>
+where
+    T: ClawType,
+{
+    /// Encodes array to base64 [`String`]
+    fn encode_string(&self, buf: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(buf)
+    }
+
+    /// Decode base64 array to [`Vec<u8>`]
+    fn decode_string(&self, buf: &[u8]) -> AppResult<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(buf)
+            .map_err(|err| AppError::err(ErrType::VaultError, err, "Error decoding base64"))
+    }
+
+    /// Generates SHA-256 hash for the data
+    fn sha256(&self, data: &[u8]) -> String {
+        hex::encode(backend::sha256(data))
+    }
+
+    /// Encrypts data using the claw's [`Suite`] under a random nonce
+    ///
+    /// Returns `nonce | ciphertext | tag`
+    fn seal(&self, provider: &dyn KeyProvider, key: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+        let cipher = self.suite.cipher();
+        let mut sealed = vec![0; cipher.nonce_len()];
+        provider.fill_bytes(&mut sealed)?;
+
+        let ciphertext = cipher.encrypt(key, &sealed, &self.version.aad(), data)?;
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    /// Generates claw key and wraps `content_key` under it
+    ///
+    /// Returns encoded claw key, encoded wrapped content key and passphrase parameters
+    fn wrap_content_key(
+        &self,
+        provider: &dyn KeyProvider,
+        content_key: &[u8],
+    ) -> AppResult<(Zeroizing<String>, String, Option<String>)> {
+        let key = provider.generate_key()?;
+
+        let params = match self.passphrase {
+            Some(_) => Some(Argon2Params::generate(provider)?),
+            None => None,
+        };
+        let wrap_key = self.wrap_key(provider, key.as_slice(), params.as_ref())?;
+
+        let wrapped = self.seal(provider, wrap_key.as_slice(), content_key)?;
+
+        Ok((
+            Zeroizing::new(self.encode_string(key.as_slice())),
+            self.encode_string(&wrapped),
+            params.map(|p| p.to_string()),
+        ))
+    }
+
+    /// Unwraps content key using claw key and passphrase
+    fn unwrap_content_key(&self, provider: &dyn KeyProvider) -> AppResult<Zeroizing<Vec<u8>>> {
+        let key = Zeroizing::new(self.decode_string(self.key.as_bytes())?);
+        let params = self.kdf.as_deref().map(str::parse::<Argon2Params>).transpose()?;
+        let wrap_key = self.wrap_key(provider, &key, params.as_ref())?;
+
+        let wrapped = self.decode_string(self.e_pem.as_bytes())?;
+        self.open(wrap_key.as_slice(), &wrapped)
+    }
+
+    /// Derives key wrapping the content key
+    ///
+    /// When `params` are given the stretched passphrase is mixed into the
+    /// claw key, so both are required to unwrap the content key.
+    fn wrap_key(
+        &self,
+        provider: &dyn KeyProvider,
+        key: &[u8],
+        params: Option<&Argon2Params>,
+    ) -> AppResult<Zeroizing<[u8; key::KEY_LEN]>> {
+        let Some(params) = params else {
+            return provider.derive_key(key, KeyPurpose::Wrap);
+        };
+        let passphrase = self.passphrase.as_deref().ok_or_else(|| {
+            AppError::new(ErrType::Unauthorized, "Passphrase is required to decrypt claw")
+        })?;
+
+        let mut ikm = Zeroizing::new(Vec::with_capacity(key.len() + key::KEY_LEN));
+        ikm.extend_from_slice(key);
+        ikm.extend_from_slice(params.stretch(passphrase)?.as_slice());
+        provider.derive_key(&ikm, KeyPurpose::Wrap)
+    }
+
+    /// Decrypts `nonce | ciphertext | tag` using the claw's [`Suite`]
+    fn open(&self, key: &[u8], sealed: &[u8]) -> AppResult<Zeroizing<Vec<u8>>> {
+        // claws prior to V3 were encrypted with AES-256-GCM without associated data
+        let (cipher, aad): (&dyn CipherSuite, &[u8]) = match self.version {
+            ClawVersion::Sealed | ClawVersion::V1 | ClawVersion::V2 => (&suite::Aes256Gcm, &[]),
+            _ => (self.suite.cipher(), &self.version.aad()),
+        };
+
+        if sealed.len() < cipher.nonce_len() + TAG_LEN {
+            return Err(AppError::new(ErrType::VaultError, "Encrypted data is malformed"));
+        }
+        let (nonce, sealed) = sealed.split_at(cipher.nonce_len());
+        cipher.decrypt(key, nonce, aad, sealed)
+    }
+}
+
+impl Vault<Init> {
+    pub fn cipher_stream() -> Vault<StreamClaw> {
+        Vault {
+            version: ClawVersion::V4,
+            suite: Suite::default(),
+            hash: None,
+            key: Zeroizing::default(),
+            data: Zeroizing::default(),
+            e_pem: String::default(),
+            kdf: None,
+            passphrase: None,
+            compress: false,
+            padding: frame::Padding::None,
+            max_size: Config::get_max_decompressed_bytes(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn cipher(data: Vec<u8>) -> Vault<EncryptClaw> {
+        Vault {
+            version: ClawVersion::CURRENT,
+            suite: Suite::default(),
+            hash: None,
+            key: Zeroizing::default(),
+            data: Zeroizing::new(data),
+            e_pem: String::default(),
+            kdf: None,
+            passphrase: None,
+            compress: false,
+            padding: frame::Padding::None,
+            max_size: Config::get_max_decompressed_bytes(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn decipher(
+        EData { version, suite, hash, key, encrypted, e_pem, kdf }: EData,
+    ) -> Vault<DecryptClaw> {
+        Vault {
+            version,
+            suite,
+            hash,
+            key,
+            data: Zeroizing::new(encrypted.into_bytes()),
+            e_pem,
+            kdf,
+            passphrase: None,
+            compress: false,
+            padding: frame::Padding::None,
+            max_size: Config::get_max_decompressed_bytes(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Vault<EncryptClaw> {
+    /// Protects the claw with a passphrase in addition to the claw key
+    pub fn with_passphrase(mut self, passphrase: Option<Zeroizing<String>>) -> Self {
+        self.passphrase = passphrase;
+        self
+    }
+
+    /// Compresses data with zstd before encryption
+    ///
+    /// Data is stored uncompressed if compression does not reduce its size
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Encrypts data and content key with `suite` instead of the default one
+    pub fn with_suite(mut self, suite: Suite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    /// Pads data to a size bucket before encryption, hiding its exact length
+    pub fn with_padding(mut self, padding: frame::Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Encrypt data and returns [`EData`]
+    ///
+    /// - Generate claw key and content key using [`KeyProvider`]
+    /// - Frame data, compressing and padding it if enabled
+    /// - Encrypt data with content key using the chosen [`Suite`]
+    /// - Derive wrapping key from claw key and stretched passphrase, if any
+    /// - Wrap content key with wrapping key using the chosen [`Suite`]
+    /// - Encode key, data and wrapped content key
+    pub fn encrypt(self, provider: &dyn KeyProvider) -> AppResult<EData> {
+        let content_key = provider.generate_key()?;
+
+        // encrypt data
+        let data = frame::encode(&self.data, self.compress, self.padding)?;
+        let encrypted = self.seal(provider, content_key.as_slice(), &data)?;
+        let encrypted = self.encode_string(&encrypted);
+
+        // wrap content key
+        let (key, wrapped, kdf) = self.wrap_content_key(provider, content_key.as_slice())?;
+
+        Ok(EData {
+            version: self.version,
+            suite: self.suite,
+            hash: self.hash,
+            key,
+            encrypted,
+            e_pem: wrapped,
+            kdf,
+        })
+    }
+}
+
+impl Vault<StreamClaw> {
+    /// Protects the claw with a passphrase in addition to the claw key
+    pub fn with_passphrase(mut self, passphrase: Option<Zeroizing<String>>) -> Self {
+        self.passphrase = passphrase;
+        self
+    }
+
+    /// Prepares a [`ClawVersion::V4`] claw and returns [`EData`] along with
+    /// [`StreamEncryptor`] to encrypt the data chunk by chunk
+    ///
+    /// [`EData::encrypted`] holds the nonce prefix of the stream
+    pub fn encrypt(self, provider: &dyn KeyProvider) -> AppResult<(EData, StreamEncryptor)> {
+        let content_key = provider.generate_key()?;
+        let mut prefix = [0; stream::PREFIX_LEN];
+        provider.fill_bytes(&mut prefix)?;
+
+        let (key, wrapped, kdf) = self.wrap_content_key(provider, content_key.as_slice())?;
+
+        let edata = EData {
+            version: self.version,
+            suite: self.suite,
+            hash: None,
+            key,
+            encrypted: self.encode_string(&prefix),
+            e_pem: wrapped,
+            kdf,
+        };
+        Ok((edata, StreamEncryptor::new(content_key, prefix, self.version.aad())))
+    }
+}
+
+impl Vault<DecryptClaw> {
+    /// Passphrase to decrypt a passphrase protected claw
+    pub fn with_passphrase(mut self, passphrase: Option<Zeroizing<String>>) -> Self {
+        self.passphrase = passphrase;
+        self
+    }
+
+    /// Maximum size in bytes compressed data may decompress to
+    ///
+    /// Defaults to [`Config::get_max_decompressed_bytes`]
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Decrypt data using the scheme named by the claw's [`ClawVersion`]
+    ///
+    /// `provider` must derive keys the same way as the one used to encrypt
+    pub fn decrypt(self, provider: &dyn KeyProvider) -> AppResult<Vault<ChecksumClaw>> {
+        let decrypted = match self.version {
+            ClawVersion::V1 => self.decrypt_v1()?,
+            ClawVersion::V2 => self.decrypt_v2()?,
+            ClawVersion::V3 => self.decrypt_v3(provider)?,
+            ClawVersion::V5 => frame::decode(self.decrypt_v3(provider)?, self.max_size)?,
+            ClawVersion::V4 => {
+                return Err(AppError::new(
+                    ErrType::VaultError,
+                    "Streamed claws must be decrypted as a stream",
+                ));
+            }
+            ClawVersion::Sealed => {
+                return Err(AppError::new(
+                    ErrType::VaultError,
+                    "Sealed claws can only be opened by the client",
+                ));
+            }
+        };
+
+        Ok(Vault {
+            version: self.version,
+            suite: self.suite,
+            hash: self.hash,
+            key: Zeroizing::default(),
+            data: decrypted,
+            e_pem: String::default(),
+            kdf: None,
+            passphrase: None,
+            compress: false,
+            padding: frame::Padding::None,
+            max_size: Config::get_max_decompressed_bytes(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Unwraps content key of a [`ClawVersion::V4`] claw and returns
+    /// [`StreamDecryptor`] to decrypt its chunks
+    pub fn decrypt_stream(self, provider: &dyn KeyProvider) -> AppResult<StreamDecryptor> {
+        if self.version != ClawVersion::V4 {
+            return Err(AppError::new(ErrType::VaultError, "Claw is not a streamed claw"));
+        }
+
+        let content_key = self.unwrap_content_key(provider)?;
+        let content_key = content_key[..]
+            .try_into()
+            .map(Zeroizing::new)
+            .map_err(|_| AppError::new(ErrType::VaultError, "Content key is malformed"))?;
+        let prefix = self
+            .decode_string(&self.data)?
+            .try_into()
+            .map_err(|_| AppError::new(ErrType::VaultError, "Stream prefix is malformed"))?;
+
+        Ok(StreamDecryptor::new(content_key, prefix, self.version.aad()))
+    }
+
+    /// Decrypt [`ClawVersion::V1`] data
+    ///
+    /// - Decrypt pem
+    /// - Decrypt data
+    fn decrypt_v1(&self) -> AppResult<Zeroizing<Vec<u8>>> {
+        let pem = self.decrypt_pem()?;
+
+        // decode encrypted data
+        let data = self.decode_string(&self.data)?;
+
+        // decrypt data
+        backend::rsa_public_decrypt(&pem, &data)
+    }
+
+    /// Decrypt [`ClawVersion::V2`] data
+    ///
+    /// - Decrypt pem
+    /// - Unwrap content key
+    /// - Decrypt data
+    fn decrypt_v2(&self) -> AppResult<Zeroizing<Vec<u8>>> {
+        let pem = self.decrypt_pem()?;
+
+        // decode encrypted data
+        let data = self.decode_string(&self.data)?;
+        let wrapped_len = backend::rsa_size(&pem)?;
+        if data.len() < wrapped_len {
+            return Err(AppError::new(ErrType::VaultError, "Encrypted data is malformed"));
+        }
+        let (wrapped, data) = data.split_at(wrapped_len);
+
+        // unwrap content key
+        let content_key = backend::rsa_public_decrypt(&pem, wrapped)?;
+
+        // decrypt data
+        self.open(&content_key, data)
+    }
+
+    /// Decrypt [`ClawVersion::V3`] data
+    ///
+    /// - Derive wrapping key from claw key
+    /// - Unwrap content key
+    /// - Decrypt data
+    fn decrypt_v3(&self, provider: &dyn KeyProvider) -> AppResult<Zeroizing<Vec<u8>>> {
+        let content_key = self.unwrap_content_key(provider)?;
+
+        // decrypt data
+        let data = self.decode_string(&self.data)?;
+        self.open(&content_key, &data)
+    }
+
+    /// Decode key and pem, returns decrypted RSA public key pem
+    fn decrypt_pem(&self) -> AppResult<Zeroizing<Vec<u8>>> {
+        // base64 decode key and pem
+        let key = Zeroizing::new(self.decode_string(self.key.as_bytes())?);
+        let pem = self.decode_string(self.e_pem.as_bytes())?;
+
+        // decrypt pem
+        XORCryptor::decrypt_v2(&key, pem)
+            .map(Zeroizing::new)
+            .map_err(|e| AppError::err(ErrType::VaultError, e, "Failed to decrypt pem"))
+    }
+}
+
+impl Vault<ChecksumClaw> {
+    /// Validate SHA-256 checksum of a [`ClawVersion::V1`] claw
+    ///
+    /// Later versions were already authenticated while decrypting
+    pub fn validate_and_get(self) -> AppResult<Zeroizing<Vec<u8>>> {
+        let expected = match (self.version, &self.hash) {
+            (_, Some(hash)) => hash.as_bytes(),
+            (ClawVersion::V1, None) => {
+                return Err(AppError::new(ErrType::BadRequest, "SHA256 checksum is missing"));
+            }
+            (_, None) => return Ok(self.data),
+        };
+
+        let dec_hash = self.sha256(&self.data);
+        if !backend::eq(dec_hash.as_bytes(), expected) {
+            return Err(AppError::new(ErrType::BadRequest, "SHA256 checksum do not match"));
+        }
+        Ok(self.data)
+    }
+}
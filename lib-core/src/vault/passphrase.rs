@@ -0,0 +1,111 @@
+use std::{fmt::Display, str::FromStr};
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::{
+    backend,
+    key::{KEY_LEN, KeyProvider},
+};
+use crate::{AppError, AppResult, ErrType, config::Config};
+
+/// Length of Argon2id salt
+const SALT_LEN: usize = 16;
+
+/// Memory cost in KiB new passphrases are stretched with
+pub const MEMCOST: u32 = 19 * 1024;
+
+/// Iterations new passphrases are stretched with
+const ITER: u32 = 2;
+
+/// Most iterations accepted in stored parameters
+const MAX_ITER: u32 = 8;
+
+/// Most lanes accepted in stored parameters
+const MAX_LANES: u32 = 8;
+
+/// Argon2id parameters used to stretch a passphrase
+///
+/// Stored alongside the claw as `argon2id$v=19$m=<memcost>,t=<iter>,p=<lanes>$<salt>`.
+/// Parsed parameters must lie within bounds, memory cost up to
+/// [`Config::get_max_kdf_memcost`], so that a stored or imported claw can't
+/// exhaust memory or CPU of whoever decrypts it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory cost in KiB
+    memcost: u32,
+    iter: u32,
+    lanes: u32,
+    salt: Vec<u8>,
+}
+
+impl Argon2Params {
+    /// Generates parameters with a random salt
+    ///
+    /// Costs follow OWASP recommendations for Argon2id
+    pub fn generate(provider: &dyn KeyProvider) -> AppResult<Self> {
+        let mut salt = vec![0; SALT_LEN];
+        provider.fill_bytes(&mut salt)?;
+        Ok(Argon2Params { memcost: MEMCOST, iter: ITER, lanes: 1, salt })
+    }
+
+    /// Stretches `passphrase` into a key
+    pub fn stretch(&self, passphrase: &str) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+        backend::argon2id(passphrase.as_bytes(), &self.salt, self.iter, self.lanes, self.memcost)
+    }
+}
+
+impl Display for Argon2Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let salt = base64::engine::general_purpose::STANDARD_NO_PAD.encode(&self.salt);
+        write!(f, "argon2id$v=19$m={},t={},p={}${}", self.memcost, self.iter, self.lanes, salt)
+    }
+}
+
+impl FromStr for Argon2Params {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || AppError::new(ErrType::VaultError, "Malformed passphrase parameters");
+
+        let mut parts = s.split('$');
+        if parts.next() != Some("argon2id") || parts.next() != Some("v=19") {
+            return Err(malformed());
+        }
+
+        let (mut memcost, mut iter, mut lanes) = (None, None, None);
+        for param in parts.next().ok_or_else(malformed)?.split(',') {
+            let (name, value) = param.split_once('=').ok_or_else(malformed)?;
+            let value = value.parse().map_err(|_| malformed())?;
+            match name {
+                "m" => memcost = Some(value),
+                "t" => iter = Some(value),
+                "p" => lanes = Some(value),
+                _ => return Err(malformed()),
+            }
+        }
+
+        let salt = base64::engine::general_purpose::STANDARD_NO_PAD
+            .decode(parts.next().ok_or_else(malformed)?)
+            .map_err(|_| malformed())?;
+
+        let (memcost, iter, lanes) = (
+            memcost.ok_or_else(malformed)?,
+            iter.ok_or_else(malformed)?,
+            lanes.ok_or_else(malformed)?,
+        );
+        // Argon2 needs at least 8 KiB per lane
+        if !(1..=MAX_LANES).contains(&lanes)
+            || !(1..=MAX_ITER).contains(&iter)
+            || !(8 * lanes..=Config::get_max_kdf_memcost()).contains(&memcost)
+            || salt.len() != SALT_LEN
+        {
+            return Err(AppError::new(
+                ErrType::VaultError,
+                "Passphrase parameters are out of bounds",
+            ));
+        }
+
+        Ok(Argon2Params { memcost, iter, lanes, salt })
+    }
+}
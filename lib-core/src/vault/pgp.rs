@@ -0,0 +1,636 @@
+//! Export of claws as OpenPGP messages
+//!
+//! Encrypts to an encryption key of an ASCII-armored v4 public key, RSA or
+//! ECDH over Curve25519, using a version 3 public-key encrypted session key
+//! packet followed by a symmetrically encrypted and integrity protected data
+//! packet, see RFC 4880 and RFC 6637.
+//!
+//! Only self-signatures that verify are taken into account, which requires an
+//! RSA or Ed25519 primary key hashing with SHA-2. The key is refused when it
+//! is revoked or expired. Out of its subkeys the newest one that is bound for
+//! encryption, neither revoked nor expired, is used, falling back to the
+//! primary key if it may encrypt itself.
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::backend::{self, Digest, sha1};
+use super::key::{KEY_LEN, KeyProvider};
+use super::recipient;
+use crate::{AppError, AppResult, ErrType};
+
+const MESSAGE_BEGIN: &str = "-----BEGIN PGP MESSAGE-----";
+const MESSAGE_END: &str = "-----END PGP MESSAGE-----";
+const PUBLIC_KEY_BEGIN: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+
+const TAG_PKESK: u8 = 1;
+const TAG_SIGNATURE: u8 = 2;
+const TAG_SKESK: u8 = 3;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_LITERAL: u8 = 11;
+const TAG_USER_ID: u8 = 13;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+const TAG_USER_ATTRIBUTE: u8 = 17;
+const TAG_SEIPD: u8 = 18;
+
+const ALG_RSA: u8 = 1;
+const ALG_RSA_ENCRYPT: u8 = 2;
+const ALG_RSA_SIGN: u8 = 3;
+const ALG_ECDH: u8 = 18;
+const ALG_EDDSA: u8 = 22;
+const ALG_AES256: u8 = 9;
+
+/// OID of Curve25519 for ECDH
+const OID_CV25519: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x97, 0x55, 0x01, 0x05, 0x01];
+/// OID of Ed25519 for EdDSA
+const OID_ED25519: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+
+const SIG_SUBKEY_BINDING: u8 = 0x18;
+const SIG_DIRECT_KEY: u8 = 0x1f;
+const SIG_KEY_REVOCATION: u8 = 0x20;
+const SIG_SUBKEY_REVOCATION: u8 = 0x28;
+
+const SUBPACKET_CREATION_TIME: u8 = 2;
+const SUBPACKET_SIG_EXPIRATION: u8 = 3;
+const SUBPACKET_KEY_EXPIRATION: u8 = 9;
+const SUBPACKET_KEY_FLAGS: u8 = 27;
+/// Subpackets understood well enough to be marked critical, a signature with
+/// any other critical subpacket is invalid
+const KNOWN_SUBPACKETS: &[u8] = &[2, 3, 9, 11, 16, 21, 22, 23, 25, 27, 30, 33, 34];
+
+/// Key flags for encryption of communications and of storage
+const FLAGS_ENCRYPT: u8 = 0x04 | 0x08;
+
+/// DER encoded `DigestInfo` prefixes of PKCS#1 v1.5 signatures
+const DIGEST_INFO_SHA256: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+const DIGEST_INFO_SHA384: [u8; 19] = [
+    0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02, 0x05,
+    0x00, 0x04, 0x30,
+];
+const DIGEST_INFO_SHA512: [u8; 19] = [
+    0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05,
+    0x00, 0x04, 0x40,
+];
+
+/// Public key material of an encryption capable key
+enum KeyMaterial {
+    Rsa {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// `fields` holds the OID and KDF parameters as they are encoded in the key
+    Ecdh {
+        point: [u8; KEY_LEN],
+        hash: u8,
+        sym: u8,
+        fields: Vec<u8>,
+    },
+}
+
+struct EncryptionKey {
+    fingerprint: [u8; 20],
+    material: KeyMaterial,
+}
+
+/// Public key material of a primary key, verifying its self-signatures
+enum VerifyingKey {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ed25519([u8; KEY_LEN]),
+}
+
+/// Hashed subpackets of a verified signature
+struct Signature {
+    sig_type: u8,
+    created: i64,
+    key_expiration: Option<i64>,
+    key_flags: Option<u8>,
+}
+
+impl Signature {
+    /// Returns whether the key created at `created` is expired at `now`
+    fn is_key_expired(&self, created: i64, now: i64) -> bool {
+        self.key_expiration.is_some_and(|secs| secs != 0 && created + secs <= now)
+    }
+
+    /// Returns whether the key may encrypt, keys without flags may do anything
+    /// their algorithm allows
+    fn allows_encryption(&self) -> bool {
+        self.key_flags.is_none_or(|flags| flags & FLAGS_ENCRYPT != 0)
+    }
+}
+
+/// Subkey packet along with its signatures
+struct Subkey<'a> {
+    body: &'a [u8],
+    signatures: Vec<&'a [u8]>,
+}
+
+/// Encrypts `data` named `filename` to armored `public_key`, returns the
+/// armored message
+pub fn encrypt(
+    provider: &dyn KeyProvider,
+    public_key: &str,
+    data: &[u8],
+    filename: &str,
+) -> AppResult<String> {
+    let key = encryption_key(&dearmor(public_key, PUBLIC_KEY_BEGIN)?)?;
+
+    let session_key = provider.generate_key()?;
+    let mut message = packet(TAG_PKESK, &pkesk(provider, &key, &session_key)?);
+    message.extend(packet(TAG_SEIPD, &seipd(provider, &session_key, data, filename)?));
+
+    Ok(armor(&message))
+}
+
+/// Returns whether `blob` looks like an OpenPGP message, armored or not
+pub fn is_message(blob: &[u8]) -> bool {
+    let blob = blob.trim_ascii_start();
+    if blob.starts_with(MESSAGE_BEGIN.as_bytes()) {
+        return true;
+    }
+    // binary messages start with an encrypted session key packet
+    match blob.first() {
+        Some(b) if b & 0xc0 == 0xc0 => matches!(b & 0x3f, TAG_PKESK | TAG_SKESK),
+        Some(b) if b & 0x80 != 0 => matches!((b >> 2) & 0x0f, TAG_PKESK | TAG_SKESK),
+        _ => false,
+    }
+}
+
+fn malformed() -> AppError {
+    AppError::new(ErrType::BadRequest, "OpenPGP public key is malformed")
+}
+
+/// Returns the newest usable encryption subkey, or the primary key if it may
+/// encrypt, see the module docs
+fn encryption_key(packets: &[u8]) -> AppResult<EncryptionKey> {
+    let invalid = |message| AppError::new(ErrType::BadRequest, message);
+
+    let (tag, primary, mut rest) = read_packet(packets)?;
+    if tag != TAG_PUBLIC_KEY {
+        return Err(malformed());
+    }
+    let verifying = verifying_key(primary)?;
+    let framed_primary = framed_key(primary);
+
+    // signatures of the primary key itself, each with the data it is over
+    let (mut primary_signatures, mut subkeys) = (vec![], Vec::<Subkey>::new());
+    let mut component: Option<Vec<u8>> = Some(framed_primary.clone());
+    while !rest.is_empty() {
+        let (tag, body, next) = read_packet(rest)?;
+        rest = next;
+
+        match tag {
+            TAG_SIGNATURE => match (&component, subkeys.last_mut()) {
+                (Some(signed), _) => primary_signatures.push((signed.clone(), body)),
+                (None, Some(subkey)) => subkey.signatures.push(body),
+                (None, None) => {}
+            },
+            TAG_USER_ID | TAG_USER_ATTRIBUTE => {
+                let prefix = if tag == TAG_USER_ID { 0xb4 } else { 0xd1 };
+                let mut signed = framed_primary.clone();
+                signed.push(prefix);
+                signed.extend_from_slice(&(body.len() as u32).to_be_bytes());
+                signed.extend_from_slice(body);
+                component = Some(signed);
+            }
+            TAG_PUBLIC_SUBKEY => {
+                component = None;
+                subkeys.push(Subkey { body, signatures: vec![] });
+            }
+            _ => {}
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let created = key_created(primary)?;
+    let signatures: Vec<Signature> = primary_signatures
+        .iter()
+        .filter_map(|(signed, body)| verify_signature(&verifying, signed, body, now))
+        .collect();
+    if signatures.iter().any(|s| s.sig_type == SIG_KEY_REVOCATION) {
+        return Err(invalid("OpenPGP public key is revoked"));
+    }
+    let self_signature = signatures
+        .into_iter()
+        .filter(|s| s.sig_type == SIG_DIRECT_KEY || (0x10..=0x13).contains(&s.sig_type))
+        .max_by_key(|s| s.created)
+        .ok_or_else(|| invalid("OpenPGP public key has no valid self-signature"))?;
+    if self_signature.is_key_expired(created, now) {
+        return Err(invalid("OpenPGP public key has expired"));
+    }
+
+    let mut usable = None;
+    for subkey in &subkeys {
+        let created = key_created(subkey.body)?;
+        let signed = [&framed_primary[..], &framed_key(subkey.body)].concat();
+        let signatures: Vec<Signature> = subkey
+            .signatures
+            .iter()
+            .filter_map(|body| verify_signature(&verifying, &signed, body, now))
+            .collect();
+        if signatures.iter().any(|s| s.sig_type == SIG_SUBKEY_REVOCATION) {
+            continue;
+        }
+        let Some(binding) = signatures
+            .into_iter()
+            .filter(|s| s.sig_type == SIG_SUBKEY_BINDING)
+            .max_by_key(|s| s.created)
+        else {
+            continue;
+        };
+        let Some(material) = key_material(subkey.body)? else {
+            continue;
+        };
+        if binding.is_key_expired(created, now) || !binding.allows_encryption() {
+            continue;
+        }
+        if usable.as_ref().is_none_or(|(newest, _)| created > *newest) {
+            usable =
+                Some((created, EncryptionKey { fingerprint: fingerprint(subkey.body), material }));
+        }
+    }
+    if let Some((_, key)) = usable {
+        return Ok(key);
+    }
+
+    match key_material(primary)? {
+        Some(material) if self_signature.allows_encryption() => {
+            Ok(EncryptionKey { fingerprint: fingerprint(primary), material })
+        }
+        _ => Err(invalid("OpenPGP public key has no usable encryption key")),
+    }
+}
+
+/// Returns key packet `body` framed as it is hashed for fingerprints and
+/// signatures
+fn framed_key(body: &[u8]) -> Vec<u8> {
+    let mut framed = vec![0x99];
+    framed.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    framed.extend_from_slice(body);
+    framed
+}
+
+fn fingerprint(body: &[u8]) -> [u8; 20] {
+    sha1(&framed_key(body))
+}
+
+/// Returns creation time of a v4 key packet
+fn key_created(body: &[u8]) -> AppResult<i64> {
+    let created = body.get(1..5).ok_or_else(malformed)?;
+    Ok(u32::from_be_bytes(created.try_into().expect("4 bytes")) as i64)
+}
+
+/// Parses material of a v4 primary key packet that verifies signatures
+fn verifying_key(body: &[u8]) -> AppResult<VerifyingKey> {
+    if body.len() < 6 || body[0] != 4 {
+        return Err(AppError::new(ErrType::BadRequest, "Only v4 OpenPGP keys are supported"));
+    }
+    let fields = &body[6..];
+
+    match body[5] {
+        ALG_RSA | ALG_RSA_SIGN => {
+            let (n, rest) = read_mpi(fields)?;
+            let (e, _) = read_mpi(rest)?;
+            Ok(VerifyingKey::Rsa { n: n.to_vec(), e: e.to_vec() })
+        }
+        ALG_EDDSA if fields.get(1..1 + OID_ED25519.len()) == Some(OID_ED25519) => {
+            let (point, _) = read_mpi(&fields[1 + OID_ED25519.len()..])?;
+            let point = point
+                .strip_prefix(&[0x40])
+                .and_then(|p| p.try_into().ok())
+                .ok_or_else(malformed)?;
+            Ok(VerifyingKey::Ed25519(point))
+        }
+        _ => Err(AppError::new(
+            ErrType::BadRequest,
+            "Only RSA and Ed25519 OpenPGP primary keys are supported",
+        )),
+    }
+}
+
+/// Verifies v4 signature packet `body` over `signed` data with `key`, returns
+/// its hashed subpackets if it is valid at `now`
+fn verify_signature(key: &VerifyingKey, signed: &[u8], body: &[u8], now: i64) -> Option<Signature> {
+    if body.first() != Some(&4) {
+        return None;
+    }
+    let (sig_type, pk_alg, hash_alg) = (*body.get(1)?, *body.get(2)?, *body.get(3)?);
+    let hashed_len = u16::from_be_bytes([*body.get(4)?, *body.get(5)?]) as usize;
+    let hashed = body.get(..6 + hashed_len)?;
+    let rest = &body[6 + hashed_len..];
+    let unhashed_len = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+    let rest = rest.get(2 + unhashed_len..)?;
+    let (left16, values) = (rest.get(..2)?, &rest[2..]);
+
+    let (digest, prefix): (_, &[u8]) = match hash_alg {
+        8 => (Digest::Sha256, &DIGEST_INFO_SHA256),
+        9 => (Digest::Sha384, &DIGEST_INFO_SHA384),
+        10 => (Digest::Sha512, &DIGEST_INFO_SHA512),
+        _ => return None,
+    };
+    let mut data = [signed, hashed, &[4, 0xff]].concat();
+    data.extend_from_slice(&(hashed.len() as u32).to_be_bytes());
+    let hash = backend::digest(digest, &data);
+    if hash[..2] != *left16 {
+        return None;
+    }
+
+    let valid = match key {
+        VerifyingKey::Rsa { n, e } if matches!(pk_alg, ALG_RSA | ALG_RSA_SIGN) => {
+            let (s, _) = read_mpi(values).ok()?;
+            let mut padded = vec![0; n.len().checked_sub(s.len())?];
+            padded.extend_from_slice(s);
+            backend::rsa_recover(n, e, &padded).is_ok_and(|m| m == [prefix, &hash].concat())
+        }
+        VerifyingKey::Ed25519(point) if pk_alg == ALG_EDDSA => {
+            let (r, rest) = read_mpi(values).ok()?;
+            let (s, _) = read_mpi(rest).ok()?;
+            let mut signature = [0; 64];
+            signature.get_mut(32 - r.len()..32)?.copy_from_slice(r);
+            signature.get_mut(64 - s.len()..)?.copy_from_slice(s);
+            backend::ed25519_verify(point, &hash, &signature)
+        }
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+
+    // a missing creation time leaves the signature in the future
+    let mut signature =
+        Signature { sig_type, created: i64::MAX, key_expiration: None, key_flags: None };
+    let mut sig_expiration = None;
+    let mut subpackets = &hashed[6..];
+    while !subpackets.is_empty() {
+        let (len, header) = match subpackets[0] as usize {
+            l if l < 192 => (l, 1),
+            l if l < 255 => (((l - 192) << 8) + *subpackets.get(1)? as usize + 192, 2),
+            _ => (u32::from_be_bytes(subpackets.get(1..5)?.try_into().ok()?) as usize, 5),
+        };
+        let subpacket = subpackets.get(header..header + len)?;
+        subpackets = &subpackets[header + len..];
+
+        let (kind, value) = subpacket.split_first()?;
+        let seconds = || Some(u32::from_be_bytes(value.get(..4)?.try_into().ok()?) as i64);
+        match kind & 0x7f {
+            SUBPACKET_CREATION_TIME => signature.created = seconds()?,
+            SUBPACKET_SIG_EXPIRATION => sig_expiration = Some(seconds()?),
+            SUBPACKET_KEY_EXPIRATION => signature.key_expiration = Some(seconds()?),
+            SUBPACKET_KEY_FLAGS => signature.key_flags = Some(*value.first().unwrap_or(&0)),
+            kind if subpacket[0] & 0x80 != 0 && !KNOWN_SUBPACKETS.contains(&kind) => return None,
+            _ => {}
+        }
+    }
+
+    // created in the future, lacking a creation time, or expired
+    if signature.created > now
+        || sig_expiration.is_some_and(|secs| secs != 0 && signature.created + secs <= now)
+    {
+        return None;
+    }
+    Some(signature)
+}
+
+/// Parses material of a v4 key packet, `None` if it can't encrypt
+fn key_material(body: &[u8]) -> AppResult<Option<KeyMaterial>> {
+    if body.len() < 6 || body[0] != 4 {
+        return Err(AppError::new(ErrType::BadRequest, "Only v4 OpenPGP keys are supported"));
+    }
+    let fields = &body[6..];
+
+    match body[5] {
+        ALG_RSA | ALG_RSA_ENCRYPT => {
+            let (n, rest) = read_mpi(fields)?;
+            let (e, _) = read_mpi(rest)?;
+            Ok(Some(KeyMaterial::Rsa { n: n.to_vec(), e: e.to_vec() }))
+        }
+        ALG_ECDH => {
+            let oid_len = *fields.first().ok_or_else(malformed)? as usize;
+            let oid = fields.get(1..1 + oid_len).ok_or_else(malformed)?;
+            if oid != OID_CV25519 {
+                return Ok(None);
+            }
+            let (point, rest) = read_mpi(&fields[1 + oid_len..])?;
+            let point = point
+                .strip_prefix(&[0x40])
+                .and_then(|p| p.try_into().ok())
+                .ok_or_else(malformed)?;
+            let kdf = rest.get(..4).ok_or_else(malformed)?;
+            if kdf[0] != 3 || kdf[1] != 1 {
+                return Err(malformed());
+            }
+
+            let mut encoded = fields[..1 + oid_len].to_vec();
+            encoded.push(ALG_ECDH);
+            encoded.extend_from_slice(kdf);
+            Ok(Some(KeyMaterial::Ecdh { point, hash: kdf[2], sym: kdf[3], fields: encoded }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Builds public-key encrypted session key packet body
+fn pkesk(
+    provider: &dyn KeyProvider,
+    key: &EncryptionKey,
+    session_key: &[u8; KEY_LEN],
+) -> AppResult<Vec<u8>> {
+    let checksum = session_key.iter().fold(0u16, |sum, b| sum.wrapping_add(*b as u16));
+    // room for the PKCS#5 padding, so the buffer never reallocates
+    let mut m = Zeroizing::new(Vec::with_capacity(KEY_LEN + 3 + 8));
+    m.push(ALG_AES256);
+    m.extend_from_slice(session_key);
+    m.extend_from_slice(&checksum.to_be_bytes());
+
+    let mut body = vec![3];
+    body.extend_from_slice(&key.fingerprint[12..]);
+
+    match &key.material {
+        KeyMaterial::Rsa { n, e } => {
+            let encrypted = backend::rsa_encrypt(n, e, &m)?;
+            body.push(ALG_RSA);
+            body.extend(mpi(&encrypted));
+        }
+        KeyMaterial::Ecdh { point, hash: hash_id, sym, fields } => {
+            let digest = match hash_id {
+                8 => Digest::Sha256,
+                9 => Digest::Sha384,
+                10 => Digest::Sha512,
+                _ => return Err(malformed()),
+            };
+            let kek_len = match sym {
+                7 => 16,
+                8 => 24,
+                9 => 32,
+                _ => return Err(malformed()),
+            };
+
+            let (ephemeral, ephemeral_public) = recipient::generate(provider)?;
+            let shared = recipient::x25519(&ephemeral, point)?;
+
+            let mut param = fields.clone();
+            param.extend_from_slice(b"Anonymous Sender    ");
+            param.extend_from_slice(&key.fingerprint);
+            let kek = Zeroizing::new(backend::digest(
+                digest,
+                &Zeroizing::new([&[0, 0, 0, 1], &shared[..], &param].concat()),
+            ));
+
+            // PKCS#5 pad to a multiple of 8 bytes for the key wrap
+            let pad = 8 - m.len() % 8;
+            m.extend(std::iter::repeat_n(pad as u8, pad));
+            let wrapped = backend::aes_key_wrap(&kek[..kek_len], &m)?;
+
+            body.push(ALG_ECDH);
+            body.extend(mpi(&[&[0x40], &ephemeral_public[..]].concat()));
+            body.push(wrapped.len() as u8);
+            body.extend(wrapped);
+        }
+    }
+    Ok(body)
+}
+
+/// Builds symmetrically encrypted integrity protected data packet body
+fn seipd(
+    provider: &dyn KeyProvider,
+    session_key: &[u8; KEY_LEN],
+    data: &[u8],
+    filename: &str,
+) -> AppResult<Vec<u8>> {
+    let filename = &filename.as_bytes()[..filename.len().min(255)];
+    let mut literal = Zeroizing::new(Vec::with_capacity(filename.len() + data.len() + 6));
+    literal.extend_from_slice(&[b'b', filename.len() as u8]);
+    literal.extend_from_slice(filename);
+    literal.extend_from_slice(&(chrono::Utc::now().timestamp() as u32).to_be_bytes());
+    literal.extend_from_slice(data);
+
+    let mut prefix = [0; 16];
+    provider.fill_bytes(&mut prefix)?;
+
+    let literal = Zeroizing::new(packet(TAG_LITERAL, &literal));
+    let mut plain = Zeroizing::new(Vec::with_capacity(literal.len() + 40));
+    plain.extend_from_slice(&prefix);
+    plain.extend_from_slice(&prefix[14..]);
+    plain.extend_from_slice(&literal);
+    // modification detection code packet
+    plain.extend_from_slice(&[0xd3, 0x14]);
+    let mdc = sha1(&plain);
+    plain.extend_from_slice(&mdc);
+
+    let encrypted = backend::aes_256_cfb_encrypt(session_key, &[0; 16], &plain)?;
+    Ok([&[1], &encrypted[..]].concat())
+}
+
+/// Encodes new format packet with `tag`
+fn packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0 | tag];
+    match body.len() {
+        len if len < 192 => packet.push(len as u8),
+        len if len < 8384 => {
+            packet.push((((len - 192) >> 8) + 192) as u8);
+            packet.push(((len - 192) & 0xff) as u8);
+        }
+        len => {
+            packet.push(0xff);
+            packet.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Reads a packet, returns its tag, body and the remaining input
+fn read_packet(input: &[u8]) -> AppResult<(u8, &[u8], &[u8])> {
+    let first = *input.first().ok_or_else(malformed)?;
+    if first & 0x80 == 0 {
+        return Err(malformed());
+    }
+
+    let (tag, len, header) = if first & 0x40 != 0 {
+        let l = input.get(1..).ok_or_else(malformed)?;
+        match *l.first().ok_or_else(malformed)? as usize {
+            l0 if l0 < 192 => (first & 0x3f, l0, 2),
+            l0 if l0 < 224 => {
+                let l1 = *l.get(1).ok_or_else(malformed)? as usize;
+                (first & 0x3f, ((l0 - 192) << 8) + l1 + 192, 3)
+            }
+            255 => {
+                let len = l.get(1..5).ok_or_else(malformed)?;
+                (first & 0x3f, u32::from_be_bytes(len.try_into().expect("4 bytes")) as usize, 6)
+            }
+            _ => return Err(malformed()),
+        }
+    } else {
+        let tag = (first >> 2) & 0x0f;
+        let size = match first & 0x03 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => return Err(malformed()),
+        };
+        let len = input.get(1..1 + size).ok_or_else(malformed)?;
+        (tag, len.iter().fold(0, |acc, b| (acc << 8) | *b as usize), 1 + size)
+    };
+
+    let body = input.get(header..header + len).ok_or_else(malformed)?;
+    Ok((tag, body, &input[header + len..]))
+}
+
+/// Reads a multiprecision integer, returns its bytes and the remaining input
+fn read_mpi(input: &[u8]) -> AppResult<(&[u8], &[u8])> {
+    let bits = input.get(..2).ok_or_else(malformed)?;
+    let len = (u16::from_be_bytes([bits[0], bits[1]]) as usize).div_ceil(8);
+    let value = input.get(2..2 + len).ok_or_else(malformed)?;
+    Ok((value, &input[2 + len..]))
+}
+
+/// Encodes `value` as multiprecision integer
+fn mpi(value: &[u8]) -> Vec<u8> {
+    let value = &value[value.iter().take_while(|b| **b == 0).count()..];
+    let bits = value.first().map(|b| (value.len() * 8) as u32 - b.leading_zeros()).unwrap_or(0);
+    [&(bits as u16).to_be_bytes()[..], value].concat()
+}
+
+/// Returns body of armored block starting with `begin`
+fn dearmor(armored: &str, begin: &str) -> AppResult<Vec<u8>> {
+    let mut lines = armored.lines().map(str::trim).skip_while(|l| *l != begin);
+    if lines.next().is_none() {
+        return Err(malformed());
+    }
+    // armor headers end with an empty line
+    let body: String = lines
+        .skip_while(|l| !l.is_empty())
+        .skip(1)
+        .take_while(|l| !l.starts_with('=') && !l.starts_with("-----"))
+        .collect();
+
+    base64::engine::general_purpose::STANDARD
+        .decode(body)
+        .map_err(|e| AppError::err(ErrType::BadRequest, e, "OpenPGP armor is malformed"))
+}
+
+/// Wraps `message` in ASCII armor with CRC-24 checksum
+fn armor(message: &[u8]) -> String {
+    let crc = message.iter().fold(0xb704ce_u32, |mut crc, b| {
+        crc ^= (*b as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= 0x1864cfb;
+            }
+        }
+        crc
+    }) & 0xffffff;
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let mut armored = format!("{}\n\n", MESSAGE_BEGIN);
+    for line in engine.encode(message).as_bytes().chunks(64) {
+        armored.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        armored.push('\n');
+    }
+    armored.push_str(&format!("={}\n{}\n", engine.encode(&crc.to_be_bytes()[1..]), MESSAGE_END));
+    armored
+}
@@ -0,0 +1,286 @@
+//! Claw keys sealed to a recipient's X25519 public key
+//!
+//! The claw key is wrapped once per recipient, so that only holders of a
+//! matching private key can recover it, even with the link in hand. A stanza
+//! is the base64 encoding of:
+//!
+//! ```text
+//! ephemeral public key (32) | nonce (12) | ciphertext | tag (16)
+//! ```
+//!
+//! The wrap key is derived with HKDF-SHA256 from the X25519 shared secret,
+//! the ephemeral and the recipient public key. Keys are accepted as base64 of
+//! the raw 32 bytes or as age `age1...` recipients and `AGE-SECRET-KEY-1...`
+//! identities, the stanza format itself is not age's.
+//!
+//! Each recipient key is registered with a [`RecipientSuite`], recipients of
+//! [`RecipientSuite::X25519MlKem768`] are sealed to with [`super::hybrid`].
+
+use std::{fmt::Display, str::FromStr};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{
+    backend::{self, Aead, TAG_LEN},
+    hybrid,
+    key::{KEY_LEN, KeyProvider, KeyPurpose},
+};
+use crate::{AppError, AppResult, ErrType};
+
+pub(super) const NONCE_LEN: usize = 12;
+
+/// Key encapsulation a recipient key is used with, stored alongside the recipient
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub enum RecipientSuite {
+    /// X25519, see this module
+    #[default]
+    #[serde(rename = "x25519")]
+    X25519 = 1,
+    /// X25519 combined with ML-KEM-768, see [`super::hybrid`]
+    #[serde(rename = "x25519-mlkem768")]
+    X25519MlKem768 = 2,
+}
+
+impl RecipientSuite {
+    /// All suites
+    pub const ALL: [RecipientSuite; 2] = [RecipientSuite::X25519, RecipientSuite::X25519MlKem768];
+
+    /// Parses `public_key` of the suite, returns it in its canonical form
+    pub fn canonical_public_key(&self, public_key: &str) -> AppResult<String> {
+        match self {
+            RecipientSuite::X25519 => parse_public_key(public_key).map(|k| encode_key(&k)),
+            RecipientSuite::X25519MlKem768 => {
+                hybrid::parse_public_key(public_key).map(|k| hybrid::encode_key(&k))
+            }
+        }
+    }
+
+    /// Seals claw `key` to `public_key` of the suite, returns base64 stanza
+    pub fn seal(
+        &self,
+        provider: &dyn KeyProvider,
+        public_key: &str,
+        key: &[u8],
+    ) -> AppResult<String> {
+        match self {
+            RecipientSuite::X25519 => seal(provider, &parse_public_key(public_key)?, key),
+            RecipientSuite::X25519MlKem768 => {
+                hybrid::seal(provider, &hybrid::parse_public_key(public_key)?, key)
+            }
+        }
+    }
+
+    /// Opens `stanza` with `private_key` of the suite, returns the claw key
+    pub fn open(
+        &self,
+        provider: &dyn KeyProvider,
+        private_key: &str,
+        stanza: &str,
+    ) -> AppResult<Zeroizing<Vec<u8>>> {
+        match self {
+            RecipientSuite::X25519 => open(provider, &*parse_private_key(private_key)?, stanza),
+            RecipientSuite::X25519MlKem768 => {
+                hybrid::open(provider, &*hybrid::parse_private_key(private_key)?, stanza)
+            }
+        }
+    }
+}
+
+impl From<RecipientSuite> for i16 {
+    fn from(suite: RecipientSuite) -> Self {
+        suite as i16
+    }
+}
+
+impl TryFrom<i16> for RecipientSuite {
+    type Error = AppError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        RecipientSuite::ALL.into_iter().find(|s| i16::from(*s) == value).ok_or_else(|| {
+            AppError::new(ErrType::VaultError, format!("Unsupported recipient suite: {}", value))
+        })
+    }
+}
+
+impl Display for RecipientSuite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RecipientSuite::X25519 => "x25519",
+            RecipientSuite::X25519MlKem768 => "x25519-mlkem768",
+        })
+    }
+}
+
+impl FromStr for RecipientSuite {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RecipientSuite::ALL.into_iter().find(|suite| suite.to_string() == s.trim()).ok_or_else(
+            || AppError::new(ErrType::VaultError, format!("Unsupported recipient suite: {}", s)),
+        )
+    }
+}
+
+/// Generates a new key pair, returns `(private key, public key)`
+pub fn generate(
+    provider: &dyn KeyProvider,
+) -> AppResult<(Zeroizing<[u8; KEY_LEN]>, [u8; KEY_LEN])> {
+    let private = provider.generate_key()?;
+    let public = public_key(&private)?;
+    Ok((private, public))
+}
+
+/// Returns public key of X25519 `private` key
+pub fn public_key(private: &[u8; KEY_LEN]) -> AppResult<[u8; KEY_LEN]> {
+    backend::x25519_public_key(private)
+}
+
+/// Encodes `key` as base64, the canonical form of stored keys
+pub fn encode_key(key: &[u8; KEY_LEN]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key)
+}
+
+/// Parses a public key given as base64 or as an `age1...` recipient
+pub fn parse_public_key(key: &str) -> AppResult<[u8; KEY_LEN]> {
+    parse_key(key, "age")
+}
+
+/// Parses a private key given as base64 or as an `AGE-SECRET-KEY-1...` identity
+pub fn parse_private_key(key: &str) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    parse_key(key, "age-secret-key-").map(Zeroizing::new)
+}
+
+/// Seals claw `key` to `recipient` public key, returns base64 stanza
+pub fn seal(
+    provider: &dyn KeyProvider,
+    recipient: &[u8; KEY_LEN],
+    key: &[u8],
+) -> AppResult<String> {
+    let (ephemeral, ephemeral_public) = generate(provider)?;
+    let wrap_key = wrap_key(provider, &ephemeral, recipient, &ephemeral_public, recipient)?;
+
+    let mut nonce = [0; NONCE_LEN];
+    provider.fill_bytes(&mut nonce)?;
+
+    let encrypted = backend::aead_encrypt(Aead::Aes256Gcm, wrap_key.as_slice(), &nonce, &[], key)?;
+
+    let stanza = [&ephemeral_public[..], &nonce, &encrypted].concat();
+    Ok(base64::engine::general_purpose::STANDARD.encode(stanza))
+}
+
+/// Opens `stanza` with the recipient's `private` key, returns the claw key
+pub fn open(
+    provider: &dyn KeyProvider,
+    private: &[u8; KEY_LEN],
+    stanza: &str,
+) -> AppResult<Zeroizing<Vec<u8>>> {
+    let stanza = base64::engine::general_purpose::STANDARD
+        .decode(stanza)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Error decoding base64"))?;
+    if stanza.len() < KEY_LEN + NONCE_LEN + TAG_LEN {
+        return Err(AppError::new(ErrType::VaultError, "Stanza is malformed"));
+    }
+
+    let (ephemeral_public, rest) = stanza.split_at(KEY_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+
+    let ephemeral_public: [u8; KEY_LEN] = ephemeral_public.try_into().expect("split at KEY_LEN");
+    let wrap_key =
+        wrap_key(provider, private, &ephemeral_public, &ephemeral_public, &public_key(private)?)?;
+
+    backend::aead_decrypt(Aead::Aes256Gcm, wrap_key.as_slice(), nonce, &[], encrypted)
+        .map_err(|_| AppError::new(ErrType::Unauthorized, "Stanza is not sealed to this key"))
+}
+
+/// Derives wrap key from the shared secret of `private` and `public`
+fn wrap_key(
+    provider: &dyn KeyProvider,
+    private: &[u8; KEY_LEN],
+    public: &[u8; KEY_LEN],
+    ephemeral_public: &[u8; KEY_LEN],
+    recipient: &[u8; KEY_LEN],
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let shared = x25519(private, public)?;
+    let ikm = Zeroizing::new([&shared[..], ephemeral_public, recipient].concat());
+    provider.derive_key(&ikm, KeyPurpose::Recipient)
+}
+
+/// Returns X25519 shared secret of `private` and `public`
+pub(super) fn x25519(
+    private: &[u8; KEY_LEN],
+    public: &[u8; KEY_LEN],
+) -> AppResult<Zeroizing<[u8; KEY_LEN]>> {
+    let shared = backend::x25519(private, public)?;
+
+    // all-zero output means `public` is a low order point
+    if shared.iter().all(|b| *b == 0) {
+        return Err(AppError::new(ErrType::VaultError, "Invalid X25519 public key"));
+    }
+    Ok(shared)
+}
+
+/// Parses a base64 key or a bech32 key with human readable part `hrp`
+fn parse_key(key: &str, hrp: &str) -> AppResult<[u8; KEY_LEN]> {
+    let key = key.trim();
+    let decoded = if key.to_lowercase().starts_with(&format!("{}1", hrp)) {
+        bech32_decode(key, hrp)
+    } else {
+        base64::engine::general_purpose::STANDARD.decode(key).ok()
+    };
+
+    decoded
+        .and_then(|k| k.try_into().ok())
+        .ok_or_else(|| AppError::new(ErrType::BadRequest, "Key is malformed"))
+}
+
+/// Decodes bech32 `s` with human readable part `hrp`, as used by age
+fn bech32_decode(s: &str, hrp: &str) -> Option<Vec<u8>> {
+    const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    if s != s.to_lowercase() && s != s.to_uppercase() {
+        return None;
+    }
+    let s = s.to_lowercase();
+    let data = s.strip_prefix(hrp)?.strip_prefix('1')?;
+    let values: Vec<u8> = data
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&x| x == c).map(|v| v as u8))
+        .collect::<Option<_>>()?;
+    if values.len() < 6 {
+        return None;
+    }
+
+    let polymod = hrp
+        .bytes()
+        .map(|c| c >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|c| c & 31))
+        .chain(values.iter().copied())
+        .fold(1u32, |chk, v| {
+            let top = chk >> 25;
+            let chk = ((chk & 0x1ffffff) << 5) ^ v as u32;
+            (0..5).filter(|i| (top >> i) & 1 == 1).fold(chk, |chk, i| chk ^ GEN[i])
+        });
+    if polymod != 1 {
+        return None;
+    }
+
+    // regroup 5 bit values into bytes, dropping the checksum
+    let (mut acc, mut bits, mut out) = (0u32, 0, Vec::new());
+    for v in &values[..values.len() - 6] {
+        acc = (acc << 5) | *v as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || (acc & ((1 << bits) - 1)) != 0 {
+        return None;
+    }
+    Some(out)
+}
@@ -0,0 +1,110 @@
+//! Client-side sealed payloads
+//!
+//! A sealed payload is encrypted by the client before it reaches the server,
+//! the server stores it as-is and hands it back opaquely. The payload is the
+//! standard base64 encoding of:
+//!
+//! ```text
+//! magic "CLAW" (4) | format (1) = 0x01 | alg (1) | nonce (12) | ciphertext | tag (16)
+//! ```
+//!
+//! - `alg` is `0x01` for AES-256-GCM or `0x02` for ChaCha20-Poly1305
+//! - the 6 header bytes are passed as associated data
+//! - the 32 byte key never leaves the client
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::{
+    backend::{self, Aead, TAG_LEN},
+    key::{KEY_LEN, KeyProvider},
+};
+use crate::{AppError, AppResult, ErrType};
+
+const MAGIC: &[u8; 4] = b"CLAW";
+const FORMAT: u8 = 0x01;
+const HEADER_LEN: usize = 6;
+const NONCE_LEN: usize = 12;
+
+/// AEAD used to seal a payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealedAlg {
+    Aes256Gcm = 0x01,
+    ChaCha20Poly1305 = 0x02,
+}
+
+impl SealedAlg {
+    fn aead(&self) -> Aead {
+        match self {
+            SealedAlg::Aes256Gcm => Aead::Aes256Gcm,
+            SealedAlg::ChaCha20Poly1305 => Aead::ChaCha20Poly1305,
+        }
+    }
+}
+
+impl TryFrom<u8> for SealedAlg {
+    type Error = AppError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(SealedAlg::Aes256Gcm),
+            0x02 => Ok(SealedAlg::ChaCha20Poly1305),
+            _ => Err(AppError::new(
+                ErrType::VaultError,
+                format!("Unsupported sealed algorithm: {}", value),
+            )),
+        }
+    }
+}
+
+/// Validates the layout of a sealed payload without decrypting it
+///
+/// Returns the decoded payload
+pub fn validate(sealed: &str) -> AppResult<Vec<u8>> {
+    let sealed = base64::engine::general_purpose::STANDARD
+        .decode(sealed)
+        .map_err(|e| AppError::err(ErrType::VaultError, e, "Error decoding base64"))?;
+
+    if sealed.len() < HEADER_LEN + NONCE_LEN + TAG_LEN || &sealed[..4] != MAGIC {
+        return Err(AppError::new(ErrType::VaultError, "Sealed payload is malformed"));
+    }
+    if sealed[4] != FORMAT {
+        return Err(AppError::new(
+            ErrType::VaultError,
+            format!("Unsupported sealed format: {}", sealed[4]),
+        ));
+    }
+    SealedAlg::try_from(sealed[5])?;
+
+    Ok(sealed)
+}
+
+/// Seals `data` with `key`, returns base64 encoded payload
+pub fn seal(
+    provider: &dyn KeyProvider,
+    alg: SealedAlg,
+    key: &[u8; KEY_LEN],
+    data: &[u8],
+) -> AppResult<String> {
+    let mut nonce = [0; NONCE_LEN];
+    provider.fill_bytes(&mut nonce)?;
+
+    let header = [MAGIC[0], MAGIC[1], MAGIC[2], MAGIC[3], FORMAT, alg as u8];
+    let ciphertext = backend::aead_encrypt(alg.aead(), key, &nonce, &header, data)?;
+
+    let mut sealed = header.to_vec();
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(base64::engine::general_purpose::STANDARD.encode(sealed))
+}
+
+/// Opens a base64 encoded payload sealed with `key`
+pub fn open(key: &[u8; KEY_LEN], sealed: &str) -> AppResult<Zeroizing<Vec<u8>>> {
+    let sealed = validate(sealed)?;
+    let alg = SealedAlg::try_from(sealed[5])?;
+
+    let (header, sealed) = sealed.split_at(HEADER_LEN);
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+    backend::aead_decrypt(alg.aead(), key, nonce, header, ciphertext)
+}
@@ -0,0 +1,115 @@
+//! Shamir's secret sharing over GF(256)
+//!
+//! Every byte of the secret is the constant term of its own random polynomial
+//! of degree `threshold - 1`, share `x` holds the evaluations of all of them
+//! at `x`. Any `threshold` shares rebuild the secret by Lagrange interpolation
+//! at zero, fewer reveal nothing about it.
+//!
+//! A share is the base64 encoding of `x (1) | y (secret length)`.
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use super::key::KeyProvider;
+use crate::{AppError, AppResult, ErrType};
+
+/// Multiplies in GF(256) modulo the AES polynomial `x^8 + x^4 + x^3 + x + 1`
+///
+/// Branch free so that timing doesn't depend on the secret
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// Returns multiplicative inverse in GF(256), `a^254`
+fn inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Splits `secret` into `shares` shares, any `threshold` of which rebuild it
+pub fn split(
+    provider: &dyn KeyProvider,
+    secret: &[u8],
+    threshold: u8,
+    shares: u8,
+) -> AppResult<Zeroizing<Vec<String>>> {
+    if threshold < 2 || threshold > shares {
+        return Err(AppError::new(
+            ErrType::BadRequest,
+            "Threshold must be at least 2 and at most the number of shares",
+        ));
+    }
+
+    // coefficients of x^1..x^(threshold - 1) for every byte of the secret
+    let mut coeffs = Zeroizing::new(vec![0; secret.len() * (threshold as usize - 1)]);
+    provider.fill_bytes(&mut coeffs)?;
+
+    let shares = (1..=shares)
+        .map(|x| {
+            let mut share = Zeroizing::new(Vec::with_capacity(secret.len() + 1));
+            share.push(x);
+            for (i, &s) in secret.iter().enumerate() {
+                let poly =
+                    &coeffs[i * (threshold as usize - 1)..(i + 1) * (threshold as usize - 1)];
+                // Horner's method, highest coefficient first
+                let y = poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c);
+                share.push(mul(y, x) ^ s);
+            }
+            base64::engine::general_purpose::STANDARD.encode(share)
+        })
+        .collect();
+    Ok(Zeroizing::new(shares))
+}
+
+/// Rebuilds secret from `shares`
+///
+/// Fewer shares than the threshold yield a wrong secret rather than an error
+pub fn combine(shares: &[String]) -> AppResult<Zeroizing<Vec<u8>>> {
+    let shares = shares
+        .iter()
+        .map(|s| base64::engine::general_purpose::STANDARD.decode(s).map(Zeroizing::new))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::err(ErrType::BadRequest, e, "Share is not valid base64"))?;
+
+    let len = shares.first().map(|s| s.len()).unwrap_or(0);
+    if shares.len() < 2 || len < 2 || shares.iter().any(|s| s.len() != len) {
+        return Err(AppError::new(ErrType::BadRequest, "Shares are malformed"));
+    }
+    let xs: Vec<u8> = shares.iter().map(|s| s[0]).collect();
+    if xs.iter().enumerate().any(|(i, x)| *x == 0 || xs[..i].contains(x)) {
+        return Err(AppError::new(ErrType::BadRequest, "Shares must be distinct"));
+    }
+
+    // Lagrange basis polynomials evaluated at zero
+    let basis: Vec<u8> = xs
+        .iter()
+        .enumerate()
+        .map(|(i, &xi)| {
+            xs.iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(1, |acc, (_, &xj)| mul(acc, mul(xj, inv(xj ^ xi))))
+        })
+        .collect();
+
+    let secret = (1..len)
+        .map(|k| shares.iter().zip(&basis).fold(0, |acc, (share, &b)| acc ^ mul(share[k], b)))
+        .collect();
+    Ok(Zeroizing::new(secret))
+}
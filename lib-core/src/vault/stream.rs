@@ -0,0 +1,97 @@
+//! Chunked encryption of data too large to be held in memory
+//!
+//! Follows the STREAM construction: every chunk is encrypted with AES-256-GCM
+//! under the content key, using a nonce of `prefix (7) | counter (4) | last (1)`.
+//! The counter orders chunks and the last flag marks the final chunk, so
+//! reordered, dropped or truncated chunks fail to decrypt.
+
+use zeroize::Zeroizing;
+
+use super::{
+    backend::{self, Aead},
+    key::KEY_LEN,
+};
+use crate::{AppError, AppResult, ErrType};
+
+/// Length of plaintext chunks
+pub const CHUNK_LEN: usize = 64 * 1024;
+/// Length of nonce prefix, stored alongside the claw
+pub(super) const PREFIX_LEN: usize = 7;
+
+/// Shared state of [`StreamEncryptor`] and [`StreamDecryptor`]
+struct Stream {
+    key: Zeroizing<[u8; KEY_LEN]>,
+    prefix: [u8; PREFIX_LEN],
+    aad: [u8; 1],
+    counter: u32,
+    finished: bool,
+}
+
+impl Stream {
+    /// Returns nonce for the next chunk and advances the counter
+    fn next_nonce(&mut self, last: bool) -> AppResult<[u8; 12]> {
+        if self.finished {
+            return Err(AppError::new(ErrType::VaultError, "Stream is already finished"));
+        }
+
+        let mut nonce = [0; 12];
+        nonce[..PREFIX_LEN].copy_from_slice(&self.prefix);
+        nonce[PREFIX_LEN..11].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[11] = last as u8;
+
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| AppError::new(ErrType::VaultError, "Stream has too many chunks"))?;
+        self.finished = last;
+        Ok(nonce)
+    }
+}
+
+/// Encrypts a stream chunk by chunk
+pub struct StreamEncryptor(Stream);
+
+impl StreamEncryptor {
+    pub(super) fn new(
+        key: Zeroizing<[u8; KEY_LEN]>,
+        prefix: [u8; PREFIX_LEN],
+        aad: [u8; 1],
+    ) -> Self {
+        StreamEncryptor(Stream { key, prefix, aad, counter: 0, finished: false })
+    }
+
+    /// Encrypts next chunk, `last` must be set for the final chunk only
+    ///
+    /// Returns `ciphertext | tag`
+    pub fn encrypt_chunk(&mut self, chunk: &[u8], last: bool) -> AppResult<Vec<u8>> {
+        let nonce = self.0.next_nonce(last)?;
+        backend::aead_encrypt(Aead::Aes256Gcm, self.0.key.as_slice(), &nonce, &self.0.aad, chunk)
+    }
+}
+
+/// Decrypts a stream chunk by chunk
+pub struct StreamDecryptor(Stream);
+
+impl StreamDecryptor {
+    pub(super) fn new(
+        key: Zeroizing<[u8; KEY_LEN]>,
+        prefix: [u8; PREFIX_LEN],
+        aad: [u8; 1],
+    ) -> Self {
+        StreamDecryptor(Stream { key, prefix, aad, counter: 0, finished: false })
+    }
+
+    /// Decrypts next chunk, `last` must be set for the final chunk only
+    pub fn decrypt_chunk(&mut self, chunk: &[u8], last: bool) -> AppResult<Zeroizing<Vec<u8>>> {
+        if chunk.len() < backend::TAG_LEN {
+            return Err(AppError::new(ErrType::VaultError, "Encrypted chunk is malformed"));
+        }
+        let nonce = self.0.next_nonce(last)?;
+        backend::aead_decrypt(Aead::Aes256Gcm, self.0.key.as_slice(), &nonce, &self.0.aad, chunk)
+    }
+
+    /// Returns whether the final chunk was decrypted
+    pub fn is_finished(&self) -> bool {
+        self.0.finished
+    }
+}
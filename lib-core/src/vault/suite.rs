@@ -0,0 +1,153 @@
+//! Authenticated ciphers claw data and content keys are encrypted with
+//!
+//! Every claw records the [`Suite`] it was encrypted with, so the suite used
+//! for new claws can change without affecting stored ones. Legacy RSA and
+//! XRC encrypted claws are told apart by their [`super::ClawVersion`] and can
+//! only be decrypted.
+
+use std::{fmt::Display, str::FromStr};
+
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305 as XChaCha,
+    aead::{Aead as _, Payload},
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::backend::{self, Aead};
+use crate::{AppError, AppResult, ErrType};
+
+/// Authenticated encryption with associated data
+pub trait CipherSuite: Send + Sync {
+    /// Length of nonce in bytes
+    fn nonce_len(&self) -> usize;
+
+    /// Encrypts `data` and returns `ciphertext | tag`
+    fn encrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], data: &[u8]) -> AppResult<Vec<u8>>;
+
+    /// Decrypts `ciphertext | tag`
+    fn decrypt(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        data: &[u8],
+    ) -> AppResult<Zeroizing<Vec<u8>>>;
+}
+
+/// AES-256-GCM with 96-bit nonces
+pub struct Aes256Gcm;
+
+impl CipherSuite for Aes256Gcm {
+    fn nonce_len(&self) -> usize {
+        12
+    }
+
+    fn encrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+        backend::aead_encrypt(Aead::Aes256Gcm, key, nonce, aad, data)
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        data: &[u8],
+    ) -> AppResult<Zeroizing<Vec<u8>>> {
+        backend::aead_decrypt(Aead::Aes256Gcm, key, nonce, aad, data)
+    }
+}
+
+/// XChaCha20-Poly1305 with 192-bit nonces
+pub struct XChaCha20Poly1305;
+
+impl XChaCha20Poly1305 {
+    fn cipher(key: &[u8]) -> AppResult<XChaCha> {
+        XChaCha::new_from_slice(key)
+            .map_err(|_| AppError::new(ErrType::VaultError, "Key is malformed"))
+    }
+}
+
+impl CipherSuite for XChaCha20Poly1305 {
+    fn nonce_len(&self) -> usize {
+        24
+    }
+
+    fn encrypt(&self, key: &[u8], nonce: &[u8], aad: &[u8], data: &[u8]) -> AppResult<Vec<u8>> {
+        Self::cipher(key)?
+            .encrypt(nonce.into(), Payload { msg: data, aad })
+            .map_err(|_| AppError::new(ErrType::VaultError, "Failed to encrypt data"))
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        data: &[u8],
+    ) -> AppResult<Zeroizing<Vec<u8>>> {
+        Self::cipher(key)?
+            .decrypt(nonce.into(), Payload { msg: data, aad })
+            .map(Zeroizing::new)
+            .map_err(|_| AppError::new(ErrType::VaultError, "Failed to decrypt data"))
+    }
+}
+
+/// Cipher suite a claw is encrypted with, stored alongside the claw
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub enum Suite {
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm = 1,
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305 = 2,
+}
+
+impl Suite {
+    /// All suites, in order of preference
+    pub const ALL: [Suite; 2] = [Suite::Aes256Gcm, Suite::XChaCha20Poly1305];
+
+    /// Returns [`CipherSuite`] implementing the suite
+    pub fn cipher(&self) -> &'static dyn CipherSuite {
+        match self {
+            Suite::Aes256Gcm => &Aes256Gcm,
+            Suite::XChaCha20Poly1305 => &XChaCha20Poly1305,
+        }
+    }
+}
+
+impl From<Suite> for i16 {
+    fn from(suite: Suite) -> Self {
+        suite as i16
+    }
+}
+
+impl TryFrom<i16> for Suite {
+    type Error = AppError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        Suite::ALL.into_iter().find(|s| i16::from(*s) == value).ok_or_else(|| {
+            AppError::new(ErrType::VaultError, format!("Unsupported cipher suite: {}", value))
+        })
+    }
+}
+
+impl Display for Suite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Suite::Aes256Gcm => "aes-256-gcm",
+            Suite::XChaCha20Poly1305 => "xchacha20-poly1305",
+        })
+    }
+}
+
+impl FromStr for Suite {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Suite::ALL.into_iter().find(|suite| suite.to_string() == s.trim()).ok_or_else(|| {
+            AppError::new(ErrType::VaultError, format!("Unsupported cipher suite: {}", s))
+        })
+    }
+}
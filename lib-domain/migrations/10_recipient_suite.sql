@@ -0,0 +1,4 @@
+-- key encapsulation each recipient key is used with, recipients predating
+-- suites hold x25519 keys
+alter table recipient
+    add column suite smallint not null default 1;
@@ -0,0 +1,4 @@
+-- validity is any number of seconds within the configured bounds instead of
+-- one of 60, 900 or 1800
+alter table claw
+    add constraint claw_validity_positive check (validity > 0);
@@ -0,0 +1,5 @@
+-- successful reads left before the claw is deleted, claws predating the
+-- counter are read once
+alter table claw
+    add column reads_left integer not null default 1
+        constraint claw_reads_left_non_negative check (reads_left >= 0);
@@ -0,0 +1,3 @@
+-- time in milliseconds before which the claw can't be decrypted, if locked
+alter table claw
+    add column not_before bigint;
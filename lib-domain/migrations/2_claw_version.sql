@@ -0,0 +1,6 @@
+-- existing claws were encrypted with the original RSA scheme
+alter table claw
+    add column version smallint not null default 1;
+
+alter table claw
+    alter column version drop default;
@@ -0,0 +1,3 @@
+-- argon2id parameters of the passphrase protecting the claw, if any
+alter table claw
+    add column kdf text;
@@ -0,0 +1,4 @@
+-- metadata of claws created from uploaded files
+alter table claw
+    add column filename text,
+    add column mime     text;
@@ -0,0 +1,9 @@
+-- encrypted chunks of streamed claws
+create table claw_chunk
+(
+    claw_id text    not null
+        references claw (id) on delete cascade,
+    idx     integer not null,
+    data    bytea   not null,
+    primary key (claw_id, idx)
+);
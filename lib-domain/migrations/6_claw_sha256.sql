@@ -0,0 +1,8 @@
+-- claws since version 2 are authenticated by AEAD tags, a plaintext hash
+-- would only let guesses of the data be confirmed
+alter table claw
+    alter column sha256 drop not null;
+
+update claw
+set sha256 = null
+where version <> 1;
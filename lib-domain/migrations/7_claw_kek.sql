@@ -0,0 +1,3 @@
+-- version of the key-encryption key wrapping pem, null if not wrapped
+alter table claw
+    add column kek_version smallint;
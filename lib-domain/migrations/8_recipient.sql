@@ -0,0 +1,22 @@
+-- X25519 public keys claws can be sealed to
+create table recipient
+(
+    id         text   not null
+        primary key,
+    name       text   not null,
+    public_key text   not null,
+    created_at bigint not null
+);
+
+-- claw key sealed to each recipient of a claw
+create table claw_recipient
+(
+    claw_id      text not null
+        references claw
+            on delete cascade,
+    recipient_id text not null
+        references recipient
+            on delete cascade,
+    stanza       text not null,
+    primary key (claw_id, recipient_id)
+);
@@ -0,0 +1,4 @@
+-- cipher suite data and content key are encrypted with, claws predating
+-- suites were encrypted with aes-256-gcm
+alter table claw
+    add column suite smallint not null default 1;
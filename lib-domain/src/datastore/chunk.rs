@@ -0,0 +1,76 @@
+use lib_core::{AppError, AppResult, ErrType};
+use sqlx::{Postgres, Row, Transaction};
+
+use super::{Claw, Datastore, NewClaw};
+
+/// Writes a streamed claw and its chunks within a single transaction
+///
+/// The claw becomes visible only once [`ClawWriter::commit`] succeeds, a
+/// dropped writer discards everything written so far.
+pub struct ClawWriter {
+    tx: Transaction<'static, Postgres>,
+    claw: Claw,
+    idx: i32,
+}
+
+impl ClawWriter {
+    pub fn claw(&self) -> &Claw {
+        &self.claw
+    }
+
+    /// Appends next encrypted chunk of the claw
+    pub async fn write_chunk(&mut self, data: Vec<u8>) -> AppResult<()> {
+        sqlx::query(r#"INSERT INTO claw_chunk (claw_id, idx, data) VALUES ($1, $2, $3)"#)
+            .bind(&self.claw.id)
+            .bind(self.idx)
+            .bind(data)
+            .execute(&mut *self.tx)
+            .await
+            .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to save claw chunk"))?;
+
+        self.idx += 1;
+        Ok(())
+    }
+
+    pub async fn commit(self) -> AppResult<Claw> {
+        self.tx
+            .commit()
+            .await
+            .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to create claw"))?;
+        Ok(self.claw)
+    }
+}
+
+impl Datastore {
+    /// Starts creating a streamed claw, see [`ClawWriter`]
+    pub async fn begin_claw(&self, claw: NewClaw) -> AppResult<ClawWriter> {
+        let mut tx = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to begin transaction"))?;
+
+        let claw = Self::__insert_claw(&mut *tx, claw)
+            .await
+            .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to create claw"))?;
+
+        Ok(ClawWriter { tx, claw, idx: 0 })
+    }
+
+    /// Returns chunk at `idx` of a streamed claw and whether it is the last one
+    pub async fn get_chunk(&self, claw_id: &str, idx: i32) -> AppResult<Option<(Vec<u8>, bool)>> {
+        let row = sqlx::query(
+            r#"SELECT data, idx = (SELECT max(idx) FROM claw_chunk WHERE claw_id = $1) AS last
+            FROM claw_chunk WHERE claw_id = $1 AND idx = $2"#,
+        )
+        .bind(claw_id)
+        .bind(idx)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to get claw chunk"))?;
+
+        row.map(|row| Ok((row.try_get("data")?, row.try_get("last")?))).transpose().map_err(
+            |e: sqlx::Error| AppError::err(ErrType::DbError, e, "Failed to read claw chunk"),
+        )
+    }
+}
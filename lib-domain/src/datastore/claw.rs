@@ -1,4 +1,10 @@
-use lib_core::{AppError, AppResult, ErrType, enums::ValidDuration};
+use chrono::{DateTime, Utc};
+use lib_core::{
+    AppError, AppResult, ErrType,
+    config::Config,
+    enums::ValidDuration,
+    vault::{ClawVersion, suite::Suite},
+};
 use nanoid::nanoid;
 use sqlx::Row;
 
@@ -8,72 +14,181 @@ pub struct Claw {
     pub id: String,
     pub expiry_at: i64,
 
+    pub version: ClawVersion,
+    pub suite: Suite,
     pub data: String,
     pub pem: String,
-    pub sha256: String,
+    /// Kept for [`ClawVersion::V1`] claws only
+    pub sha256: Option<String>,
+    pub kdf: Option<String>,
+    pub filename: Option<String>,
+    pub mime: Option<String>,
+    /// Version of the KEK wrapping `pem`, see [`lib_core::vault::kek`]
+    pub kek_version: Option<i16>,
     pub validity: ValidDuration,
+    /// Successful reads left before the claw is deleted
+    pub reads_left: i32,
+    /// Time in milliseconds before which the claw can't be decrypted
+    pub not_before: Option<i64>,
+}
+
+/// Claw to be saved with [`Datastore::save_claw`]
+pub struct NewClaw {
+    pub version: ClawVersion,
+    pub suite: Suite,
+    pub data: String,
+    pub pem: String,
+    pub kdf: Option<String>,
+    pub filename: Option<String>,
+    pub mime: Option<String>,
+    pub kek_version: Option<i16>,
+    pub validity: ValidDuration,
+    /// Successful reads allowed before the claw is deleted
+    pub max_reads: i32,
+    /// Time before which the claw can't be decrypted, see
+    /// [`Config::is_expiry_from_unlock`] for when its validity starts
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+/// Outcome of a batch of [`Datastore::rewrap_claws`]
+pub struct RewrapBatch {
+    /// Number of claws re-wrapped
+    pub rewrapped: usize,
+    /// Ids of claws that failed to re-wrap
+    pub failed: Vec<String>,
+    /// Number of claws still to be re-wrapped, not counting failed ones
+    pub pending: i64,
+}
+
+impl Claw {
+    /// Returns time the claw expires at
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.expiry_at).unwrap_or_default()
+    }
+
+    /// Returns whether the claw outlived its validity
+    pub fn is_expired(&self) -> bool {
+        Utc::now().timestamp_millis() >= self.expiry_at
+    }
+
+    /// Returns time the claw unlocks at, if it is still locked
+    pub fn locked_until(&self) -> Option<DateTime<Utc>> {
+        self.not_before.and_then(DateTime::from_timestamp_millis).filter(|at| *at > Utc::now())
+    }
 }
 
 impl<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> for Claw {
     fn from_row(row: &'r sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
         let id: String = row.try_get("id")?;
         let expiry_at: i64 = row.try_get("expiry_at")?;
+        let version: i16 = row.try_get("version")?;
+        let version = ClawVersion::try_from(version).map_err(|e| sqlx::Error::ColumnDecode {
+            index: "version".into(),
+            source: e.get_messages().0.into(),
+        })?;
+        let suite: i16 = row.try_get("suite")?;
+        let suite = Suite::try_from(suite).map_err(|e| sqlx::Error::ColumnDecode {
+            index: "suite".into(),
+            source: e.get_messages().0.into(),
+        })?;
         let data: String = row.try_get("data")?;
         let pem: String = row.try_get("pem")?;
-        let sha256: String = row.try_get("sha256")?;
+        let sha256: Option<String> = row.try_get("sha256")?;
+        let kdf: Option<String> = row.try_get("kdf")?;
+        let filename: Option<String> = row.try_get("filename")?;
+        let mime: Option<String> = row.try_get("mime")?;
+        let kek_version: Option<i16> = row.try_get("kek_version")?;
         let validity: i32 = row.try_get("validity")?;
-        let validity: ValidDuration = validity.into();
+        let validity =
+            ValidDuration::try_from(validity).map_err(|e| sqlx::Error::ColumnDecode {
+                index: "validity".into(),
+                source: e.get_messages().0.into(),
+            })?;
+        let reads_left: i32 = row.try_get("reads_left")?;
+        let not_before: Option<i64> = row.try_get("not_before")?;
 
-        Ok(Claw { id, expiry_at, data, pem, sha256, validity })
+        Ok(Claw {
+            id,
+            expiry_at,
+            version,
+            suite,
+            data,
+            pem,
+            sha256,
+            kdf,
+            filename,
+            mime,
+            kek_version,
+            validity,
+            reads_left,
+            not_before,
+        })
     }
 }
 
 impl Datastore {
-    pub async fn save_claw(
-        &self,
-        data: String,
-        pem: String,
-        sha256: String,
-        validity: ValidDuration,
-    ) -> AppResult<Claw> {
-        let id = nanoid!(20);
-
-        let validity = validity.get_duration();
-        let expiry_at = chrono::Utc::now().timestamp_millis() + (validity as i64 * 1000);
-
-        let claw = sqlx::query_as(
-            r#"INSERT INTO claw (id, expiry_at, data, pem, sha256, validity)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, expiry_at, data, pem, sha256, validity"#,
-        )
-        .bind(id)
-        .bind(expiry_at)
-        .bind(data)
-        .bind(pem)
-        .bind(sha256)
-        .bind(validity)
-        .fetch_one(&self.db)
-        .await
-        .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to create claw"))?;
-
-        Ok(claw)
+    pub async fn save_claw(&self, claw: NewClaw) -> AppResult<Claw> {
+        Self::__insert_claw(&self.db, claw)
+            .await
+            .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to create claw"))
     }
 
+    /// Returns claw `id`, deleting it instead if it has expired
+    ///
+    /// The cleaner only runs every few seconds, so expiry is enforced here to
+    /// the millisecond. Claws without reads left are not returned either.
     pub async fn get_claw(&self, id: &str) -> AppResult<Option<Claw>> {
-        let claw = match sqlx::query_as(r#"SELECT * FROM claw WHERE id = $1"#)
-            .bind(id)
-            .fetch_one(&self.db)
-            .await
-        {
-            Ok(c) => c,
-            Err(e) => match e {
-                sqlx::Error::RowNotFound => return Ok(None),
-                _ => return Err(AppError::err(ErrType::DbError, e, "Failed to create claw")),
-            },
-        };
+        let claw: Claw =
+            match sqlx::query_as(r#"SELECT * FROM claw WHERE id = $1 AND reads_left > 0"#)
+                .bind(id)
+                .fetch_one(&self.db)
+                .await
+            {
+                Ok(c) => c,
+                Err(e) => match e {
+                    sqlx::Error::RowNotFound => return Ok(None),
+                    _ => return Err(AppError::err(ErrType::DbError, e, "Failed to create claw")),
+                },
+            };
+        if claw.is_expired() {
+            self.delete_claw(&claw.id).await?;
+            tracing::info!("Deleted expired claw on read: {}", claw.id);
+            return Ok(None);
+        }
         Ok(Some(claw))
     }
 
+    /// Reads claw `id` with `read` and takes one read off it, deleting the
+    /// claw once no reads are left
+    ///
+    /// The claw is locked while `read` runs, so concurrent readers take turns
+    /// and find the claw gone once it is used up, instead of each reading it.
+    /// Nothing is taken off the claw if `read` fails, e.g. on a wrong key.
+    /// `read` runs on the blocking pool as it may stretch a passphrase.
+    /// Returns what `read` returned, `None` if the claw is gone or expired.
+    pub async fn consume_claw<T: Send + 'static>(
+        &self,
+        id: &str,
+        read: impl FnOnce(Claw) -> AppResult<T> + Send + 'static,
+    ) -> AppResult<Option<T>> {
+        let value = self.__consume_claw(id, read, true).await?;
+        Ok(value.map(|(value, _)| value))
+    }
+
+    /// Like [`Datastore::consume_claw`], but keeps the claw once its last read
+    /// was taken, so that its chunks can still be streamed
+    ///
+    /// Such a claw is gone for every other reader and is to be deleted with
+    /// [`Datastore::delete_claw`] once streamed. Returns the reads left along
+    /// with what `read` returned.
+    pub async fn claim_claw<T: Send + 'static>(
+        &self,
+        id: &str,
+        read: impl FnOnce(Claw) -> AppResult<T> + Send + 'static,
+    ) -> AppResult<Option<(T, i32)>> {
+        self.__consume_claw(id, read, false).await
+    }
+
     pub async fn delete_claw(&self, id: &str) -> AppResult<()> {
         let _ = Self::__delete_claw(&self.db, id)
             .await
@@ -81,8 +196,105 @@ impl Datastore {
         Ok(())
     }
 
+    /// Returns ids of live claws not yet wrapped with KEK `active`
+    pub async fn get_claws_to_rewrap(&self, active: i16) -> AppResult<Vec<String>> {
+        sqlx::query_scalar(
+            r#"SELECT id FROM claw
+            WHERE version <> $1 AND kek_version IS DISTINCT FROM $2 AND expiry_at > $3"#,
+        )
+        .bind(i16::from(ClawVersion::Sealed))
+        .bind(active)
+        .bind(Utc::now().timestamp_millis())
+        .fetch_all(&self.db)
+        .await
+        .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to list claws to rewrap"))
+    }
+
+    /// Re-wraps `pem` of up to `limit` live claws of `ids` not yet wrapped
+    /// with KEK `active`, using `rewrap` to turn `(kek_version, pem)` into the
+    /// new pair
+    ///
+    /// Claws are locked for the duration of one batch only, claws locked by
+    /// readers are skipped and claws in `failed` are left out. A claw `rewrap`
+    /// fails for is reported in [`RewrapBatch::failed`] instead of aborting
+    /// the batch.
+    pub async fn rewrap_claws(
+        &self,
+        active: i16,
+        limit: i64,
+        ids: &[String],
+        failed: &[String],
+        rewrap: impl Fn(Option<i16>, String) -> AppResult<(Option<i16>, String)>,
+    ) -> AppResult<RewrapBatch> {
+        let db_err = |e| AppError::err(ErrType::DbError, e, "Failed to rewrap claws");
+        let now = Utc::now().timestamp_millis();
+
+        let mut tx = self.db.begin().await.map_err(db_err)?;
+        let rows = sqlx::query(
+            r#"SELECT id, kek_version, pem FROM claw
+            WHERE version <> $1 AND kek_version IS DISTINCT FROM $2 AND expiry_at > $3
+            AND id = ANY($4) AND id <> ALL($5)
+            LIMIT $6 FOR UPDATE SKIP LOCKED"#,
+        )
+        .bind(i16::from(ClawVersion::Sealed))
+        .bind(active)
+        .bind(now)
+        .bind(ids)
+        .bind(failed)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+        let mut batch = RewrapBatch { rewrapped: 0, failed: vec![], pending: 0 };
+        for row in rows.iter() {
+            let id: String = row.try_get("id").map_err(db_err)?;
+            let rewrapped = rewrap(
+                row.try_get("kek_version").map_err(db_err)?,
+                row.try_get("pem").map_err(db_err)?,
+            );
+            let (kek_version, pem) = match rewrapped {
+                Ok(rewrapped) => rewrapped,
+                Err(err) => {
+                    let (err, err_msg, _) = err.get_messages();
+                    tracing::error!(message = "Failed to rewrap claw", id, err, err_msg);
+                    batch.failed.push(id);
+                    continue;
+                }
+            };
+
+            sqlx::query(r#"UPDATE claw SET kek_version = $1, pem = $2 WHERE id = $3"#)
+                .bind(kek_version)
+                .bind(pem)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+            batch.rewrapped += 1;
+        }
+
+        // counts claws locked by readers too, which the next batch retries
+        batch.pending = sqlx::query_scalar(
+            r#"SELECT count(*) FROM claw
+            WHERE version <> $1 AND kek_version IS DISTINCT FROM $2 AND expiry_at > $3
+            AND id = ANY($4) AND id <> ALL($5) AND id <> ALL($6)"#,
+        )
+        .bind(i16::from(ClawVersion::Sealed))
+        .bind(active)
+        .bind(now)
+        .bind(ids)
+        .bind(failed)
+        .bind(&batch.failed)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+        tx.commit().await.map_err(db_err)?;
+        Ok(batch)
+    }
+
     pub(super) async fn __delete_claw(
-        db: &sqlx::PgPool,
+        db: impl sqlx::PgExecutor<'_>,
         id: &str,
     ) -> Result<Option<()>, sqlx::Error> {
         let res = sqlx::query(r#"DELETE FROM claw WHERE id = $1"#).bind(id).execute(db).await;
@@ -97,4 +309,97 @@ impl Datastore {
             Err(err) => Err(err),
         }
     }
+
+    async fn __consume_claw<T: Send + 'static>(
+        &self,
+        id: &str,
+        read: impl FnOnce(Claw) -> AppResult<T> + Send + 'static,
+        delete_used_up: bool,
+    ) -> AppResult<Option<(T, i32)>> {
+        let db_err = |e| AppError::err(ErrType::DbError, e, "Failed to consume claw");
+
+        let mut tx = self.db.begin().await.map_err(db_err)?;
+        let claw: Option<Claw> =
+            sqlx::query_as(r#"SELECT * FROM claw WHERE id = $1 AND reads_left > 0 FOR UPDATE"#)
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        let Some(claw) = claw else {
+            return Ok(None);
+        };
+        if claw.is_expired() {
+            Self::__delete_claw(&mut *tx, id).await.map_err(db_err)?;
+            tx.commit().await.map_err(db_err)?;
+            tracing::info!("Deleted expired claw on read: {}", id);
+            return Ok(None);
+        }
+
+        // dropping the transaction on error rolls back and releases the claw
+        let value = tokio::task::spawn_blocking(move || read(claw))
+            .await
+            .map_err(|e| AppError::err(ErrType::ServerError, e, "Failed to read claw"))??;
+
+        let reads_left = Self::__take_read(&mut tx, id, delete_used_up).await.map_err(db_err)?;
+        tx.commit().await.map_err(db_err)?;
+        Ok(reads_left.map(|reads_left| (value, reads_left)))
+    }
+
+    async fn __take_read(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: &str,
+        delete_used_up: bool,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let reads_left: Option<i32> = sqlx::query_scalar(
+            r#"UPDATE claw SET reads_left = reads_left - 1
+            WHERE id = $1 AND reads_left > 0 AND expiry_at > $2
+            RETURNING reads_left"#,
+        )
+        .bind(id)
+        .bind(Utc::now().timestamp_millis())
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        if delete_used_up && reads_left == Some(0) {
+            Self::__delete_claw(&mut **tx, id).await?;
+        }
+        Ok(reads_left)
+    }
+
+    pub(super) async fn __insert_claw(
+        db: impl sqlx::PgExecutor<'_>,
+        claw: NewClaw,
+    ) -> Result<Claw, sqlx::Error> {
+        let id = nanoid!(20);
+
+        let validity = claw.validity.get_duration();
+        let starts_at = match claw.not_before {
+            Some(at) if Config::is_expiry_from_unlock() => at,
+            _ => Utc::now(),
+        };
+        let expiry_at = starts_at.timestamp_millis() + (validity as i64 * 1000);
+
+        sqlx::query_as(
+            r#"INSERT INTO claw
+            (id, expiry_at, version, suite, data, pem, kdf, filename, mime, kek_version, validity,
+            reads_left, not_before)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING *"#,
+        )
+        .bind(id)
+        .bind(expiry_at)
+        .bind(i16::from(claw.version))
+        .bind(i16::from(claw.suite))
+        .bind(claw.data)
+        .bind(claw.pem)
+        .bind(claw.kdf)
+        .bind(claw.filename)
+        .bind(claw.mime)
+        .bind(claw.kek_version)
+        .bind(validity)
+        .bind(claw.max_reads)
+        .bind(claw.not_before.map(|at| at.timestamp_millis()))
+        .fetch_one(db)
+        .await
+    }
 }
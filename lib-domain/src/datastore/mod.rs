@@ -1,10 +1,15 @@
-use claw::Claw;
+pub use chunk::ClawWriter;
+pub use claw::{Claw, NewClaw, RewrapBatch};
 use lib_core::config::Config;
+pub use recipient::Recipient;
 
+mod chunk;
 mod claw;
+mod recipient;
 
 const RECUR_SPAN_SEC: u64 = 30;
 
+#[derive(Clone)]
 pub struct Datastore {
     db: sqlx::PgPool,
 }
@@ -36,33 +41,20 @@ impl Datastore {
     }
 
     async fn __cleaner_job(db: &sqlx::PgPool) {
-        let claws: Vec<Claw> =
-            sqlx::query_as(r#"SELECT * FROM claw"#).fetch_all(db).await.unwrap_or(vec![]);
-
-        for claw in claws.into_iter() {
-            let time = chrono::Utc::now().timestamp_millis();
-
-            if time >= claw.expiry_at {
-                match Self::__delete_claw(db, &claw.id).await {
-                    Ok(res) => {
-                        if let Some(_) = res {
-                            tracing::info!("Deleted expired claw: {}", claw.id);
-                        } else {
-                            tracing::warn!(
-                                "Attempted to deleted but rows affected 0 for expired claw: {}",
-                                claw.id
-                            );
-                        }
-                    }
-
-                    Err(err) => {
-                        tracing::error!(
-                            message = "Failed to delete claw",
-                            id = claw.id,
-                            err = err.to_string(),
-                        );
-                    }
-                };
+        let deleted: Result<Vec<String>, _> =
+            sqlx::query_scalar(r#"DELETE FROM claw WHERE expiry_at <= $1 RETURNING id"#)
+                .bind(chrono::Utc::now().timestamp_millis())
+                .fetch_all(db)
+                .await;
+
+        match deleted {
+            Ok(ids) => {
+                for id in ids {
+                    tracing::info!("Deleted expired claw: {}", id);
+                }
+            }
+            Err(err) => {
+                tracing::error!(message = "Failed to delete expired claws", err = err.to_string())
             }
         }
     }
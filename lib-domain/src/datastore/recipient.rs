@@ -0,0 +1,105 @@
+use lib_core::{AppError, AppResult, ErrType, vault::recipient::RecipientSuite};
+use nanoid::nanoid;
+use sqlx::Row;
+
+use super::{Claw, Datastore, NewClaw};
+
+pub struct Recipient {
+    pub id: String,
+    pub name: String,
+    pub suite: RecipientSuite,
+    /// Base64 of the raw public key of `suite`
+    pub public_key: String,
+    pub created_at: i64,
+}
+
+impl<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> for Recipient {
+    fn from_row(row: &'r sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
+        let suite: i16 = row.try_get("suite")?;
+        let suite = RecipientSuite::try_from(suite).map_err(|e| sqlx::Error::ColumnDecode {
+            index: "suite".into(),
+            source: e.get_messages().0.into(),
+        })?;
+
+        Ok(Recipient {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            suite,
+            public_key: row.try_get("public_key")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+impl Datastore {
+    pub async fn save_recipient(
+        &self,
+        name: String,
+        suite: RecipientSuite,
+        public_key: String,
+    ) -> AppResult<Recipient> {
+        sqlx::query_as(
+            r#"INSERT INTO recipient (id, name, suite, public_key, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *"#,
+        )
+        .bind(nanoid!(20))
+        .bind(name)
+        .bind(i16::from(suite))
+        .bind(public_key)
+        .bind(chrono::Utc::now().timestamp_millis())
+        .fetch_one(&self.db)
+        .await
+        .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to create recipient"))
+    }
+
+    pub async fn get_recipients(&self, ids: &[String]) -> AppResult<Vec<Recipient>> {
+        sqlx::query_as(r#"SELECT * FROM recipient WHERE id = ANY($1)"#)
+            .bind(ids)
+            .fetch_all(&self.db)
+            .await
+            .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to get recipients"))
+    }
+
+    /// Saves claw along with its key sealed to each recipient as
+    /// `(recipient id, stanza)`
+    pub async fn save_claw_for(
+        &self,
+        claw: NewClaw,
+        stanzas: Vec<(String, String)>,
+    ) -> AppResult<Claw> {
+        let db_err = |e| AppError::err(ErrType::DbError, e, "Failed to create claw");
+
+        let mut tx = self.db.begin().await.map_err(db_err)?;
+        let claw = Self::__insert_claw(&mut *tx, claw).await.map_err(db_err)?;
+        for (recipient_id, stanza) in stanzas {
+            sqlx::query(
+                r#"INSERT INTO claw_recipient (claw_id, recipient_id, stanza) VALUES ($1, $2, $3)"#,
+            )
+            .bind(&claw.id)
+            .bind(recipient_id)
+            .bind(stanza)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
+        tx.commit().await.map_err(db_err)?;
+
+        Ok(claw)
+    }
+
+    /// Returns claw key sealed to the recipient, if the claw was sealed to it
+    /// and hasn't expired
+    pub async fn get_stanza(&self, claw_id: &str, recipient_id: &str) -> AppResult<Option<String>> {
+        sqlx::query_scalar(
+            r#"SELECT cr.stanza FROM claw_recipient cr JOIN claw c ON c.id = cr.claw_id
+            WHERE cr.claw_id = $1 AND cr.recipient_id = $2 AND c.expiry_at > $3"#,
+        )
+        .bind(claw_id)
+        .bind(recipient_id)
+        .bind(chrono::Utc::now().timestamp_millis())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| AppError::err(ErrType::DbError, e, "Failed to get stanza"))
+    }
+}
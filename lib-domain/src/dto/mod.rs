@@ -1 +1,2 @@
+pub mod recipient;
 pub mod vault;
@@ -0,0 +1,44 @@
+pub mod res {
+    use lib_core::vault::recipient::RecipientSuite;
+    use serde::Serialize;
+    use utoipa::ToSchema;
+
+    #[derive(Serialize, ToSchema)]
+    pub struct RecipientResponse {
+        pub id: String,
+        pub name: String,
+        pub suite: RecipientSuite,
+        /// Base64 of the raw public key
+        pub public_key: String,
+    }
+
+    /// Claw key sealed to a recipient
+    ///
+    /// Open it with the recipient's private key using
+    /// `lib_core::vault::recipient::RecipientSuite::open`, then decrypt the
+    /// claw with the key
+    #[derive(Serialize, ToSchema)]
+    pub struct StanzaResponse {
+        pub claw_id: String,
+        pub recipient_id: String,
+        pub stanza: String,
+    }
+}
+
+pub mod req {
+    use lib_core::vault::recipient::RecipientSuite;
+    use serde::Deserialize;
+    use utoipa::ToSchema;
+    use validator::Validate;
+
+    #[derive(Deserialize, ToSchema, Validate)]
+    pub struct RecipientRequest {
+        #[validate(length(min = 1, max = 100))]
+        pub name: String,
+        /// Key encapsulation the key is used with, defaults to `x25519`
+        pub suite: Option<RecipientSuite>,
+        /// Public key as base64 of its raw bytes, an X25519 key may also be
+        /// given as an age `age1...` recipient
+        pub public_key: String,
+    }
+}
@@ -1,35 +1,257 @@
 pub mod res {
+    use futures::stream::BoxStream;
+    use lib_core::AppResult;
     use serde::Serialize;
     use utoipa::ToSchema;
+    use zeroize::Zeroizing;
 
     #[derive(Serialize, ToSchema)]
     pub struct EncryptResponse {
+        /// ID of the claw, absent if only an export was requested
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub id: Option<String>,
+        /// Key to decrypt the claw, absent if it was split into `shares`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        pub key: Option<Zeroizing<String>>,
+        /// Shamir shares of the key, see [`super::req::KeySplit`]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<Vec<String>>)]
+        pub shares: Option<Zeroizing<Vec<String>>>,
+        /// Data sealed as requested by [`super::req::ExportRequest`]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub export: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub valid_for: Option<String>,
+        /// Time the claw expires at, RFC 3339
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[schema(format = DateTime)]
+        pub expires_at: Option<String>,
+    }
+
+    #[derive(Serialize, ToSchema)]
+    pub struct DecryptResponse {
+        #[schema(value_type = String)]
+        pub data: Zeroizing<String>,
+    }
+
+    /// Decrypted file, sent back as raw bytes
+    pub struct DecryptFileResponse {
+        pub data: Zeroizing<Vec<u8>>,
+        pub filename: Option<String>,
+        pub mime: Option<String>,
+    }
+
+    /// Decrypted file, streamed back chunk by chunk
+    pub struct DecryptStreamResponse {
+        pub stream: BoxStream<'static, AppResult<Zeroizing<Vec<u8>>>>,
+        pub filename: Option<String>,
+        pub mime: Option<String>,
+    }
+
+    #[derive(Serialize, ToSchema)]
+    pub struct ClawResponse {
+        pub id: String,
+        /// Whether a passphrase must be supplied to decrypt the claw
+        pub passphrase_required: bool,
+        /// Successful decryptions left before the claw is deleted
+        pub reads_left: i32,
+        /// Time the claw can be decrypted from if it is still locked, RFC 3339
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[schema(format = DateTime)]
+        pub unlocks_at: Option<String>,
+    }
+
+    #[derive(Serialize, ToSchema)]
+    pub struct SealedEncryptResponse {
         pub id: String,
-        pub key: String,
         pub valid_for: String,
+        /// Time the claw expires at, RFC 3339
+        #[schema(format = DateTime)]
+        pub expires_at: String,
     }
 
     #[derive(Serialize, ToSchema)]
-    pub struct DecryptResponse {
+    pub struct SealedDecryptResponse {
+        /// Sealed payload exactly as it was submitted
         pub data: String,
     }
 }
 
 pub mod req {
     use lib_core::enums::ValidDuration;
+    use lib_core::vault::suite::Suite;
     use serde::Deserialize;
+    use utoipa::IntoParams;
     use utoipa::ToSchema;
-    use validator::Validate;
+    use validator::{Validate, ValidationError};
+    use zeroize::Zeroizing;
 
     #[derive(Deserialize, ToSchema, Validate)]
     pub struct EncryptRequest {
-        pub data: String,
+        #[schema(value_type = String)]
+        pub data: Zeroizing<String>,
+        pub validity: ValidDuration,
+        /// Optional passphrase required in addition to the key to decrypt
+        #[validate(custom(function = "non_empty"))]
+        #[schema(value_type = Option<String>)]
+        pub passphrase: Option<Zeroizing<String>>,
+        /// Optionally split the key into Shamir shares instead of returning it
+        #[validate(nested)]
+        pub split: Option<KeySplit>,
+        /// Optionally seal the key to registered recipients instead of returning it
+        #[validate(length(min = 1))]
+        pub recipients: Option<Vec<String>>,
+        /// Optionally also return the data sealed as an age file or OpenPGP message
+        #[validate(nested)]
+        pub export: Option<ExportRequest>,
+        /// Cipher suite to encrypt with, defaults to the one configured on the server
+        pub suite: Option<Suite>,
+        /// Successful decryptions allowed before the claw is deleted, defaults to 1
+        #[validate(range(min = 1, max = 100))]
+        pub max_reads: Option<i32>,
+        /// Time before which the claw can't be decrypted, RFC 3339
+        #[schema(format = DateTime)]
+        pub not_before: Option<String>,
+    }
+
+    /// Export of the data to a recipient outside of claw vault
+    #[derive(Deserialize, ToSchema, Validate)]
+    pub struct ExportRequest {
+        pub format: ExportFormat,
+        /// age `age1...` recipient or base64 X25519 key for [`ExportFormat::Age`],
+        /// ASCII-armored public key for [`ExportFormat::OpenPgp`]
+        #[validate(length(min = 1))]
+        pub recipient: String,
+        /// Whether to store the claw too, defaults to `true`. Only the export is
+        /// returned otherwise.
+        #[serde(default = "default_link")]
+        pub link: bool,
+    }
+
+    fn default_link() -> bool {
+        true
+    }
+
+    #[derive(Clone, Copy, Deserialize, ToSchema)]
+    pub enum ExportFormat {
+        /// ASCII-armored age file
+        #[serde(rename = "age")]
+        Age,
+        /// ASCII-armored OpenPGP message
+        #[serde(rename = "openpgp")]
+        OpenPgp,
+    }
+
+    /// Split of the claw key into `shares` Shamir shares, any `threshold` of
+    /// which are required to decrypt
+    #[derive(Deserialize, ToSchema, Validate)]
+    #[validate(schema(function = "validate_split"))]
+    pub struct KeySplit {
+        #[validate(range(min = 2))]
+        pub shares: u8,
+        #[validate(range(min = 2))]
+        pub threshold: u8,
+    }
+
+    fn validate_split(split: &KeySplit) -> Result<(), ValidationError> {
+        if split.threshold > split.shares {
+            return Err(ValidationError::new("threshold")
+                .with_message("Threshold must not exceed the number of shares".into()));
+        }
+        Ok(())
+    }
+
+    /// Rejects empty secrets, `length` can't see through [`Zeroizing`]
+    fn non_empty(value: &Zeroizing<String>) -> Result<(), ValidationError> {
+        if value.is_empty() {
+            return Err(ValidationError::new("length").with_message("Must not be empty".into()));
+        }
+        Ok(())
+    }
+
+    /// Multipart form to encrypt a file
+    ///
+    /// `filename` and `mime` are taken from the headers of the `file` part
+    #[derive(ToSchema, Validate)]
+    pub struct EncryptFileRequest {
+        #[schema(value_type = String, format = Binary)]
+        pub file: Zeroizing<Vec<u8>>,
+        #[schema(ignore)]
+        pub filename: Option<String>,
+        #[schema(ignore)]
+        pub mime: Option<String>,
         pub validity: ValidDuration,
+        #[validate(custom(function = "non_empty"))]
+        #[schema(value_type = Option<String>)]
+        pub passphrase: Option<Zeroizing<String>>,
+        /// Cipher suite to encrypt with, defaults to the one configured on the server
+        pub suite: Option<Suite>,
+        /// Successful decryptions allowed before the claw is deleted, defaults to 1
+        #[validate(range(min = 1, max = 100))]
+        pub max_reads: Option<i32>,
+        /// Time before which the claw can't be decrypted, RFC 3339
+        #[schema(format = DateTime)]
+        pub not_before: Option<String>,
     }
 
+    /// Query of a streamed file upload, the file itself is the raw request body
+    ///
+    /// `mime` is taken from `Content-Type` and `passphrase` from `X-Claw-Passphrase`
+    #[derive(Deserialize, IntoParams, Validate)]
+    #[into_params(parameter_in = Query)]
+    pub struct EncryptStreamRequest {
+        /// Seconds or an ISO-8601 duration
+        #[param(value_type = String)]
+        pub validity: ValidDuration,
+        #[validate(length(min = 1, max = 255))]
+        pub filename: Option<String>,
+        #[serde(skip)]
+        #[param(ignore)]
+        pub mime: Option<String>,
+        #[serde(skip)]
+        #[param(ignore)]
+        #[validate(custom(function = "non_empty"))]
+        pub passphrase: Option<Zeroizing<String>>,
+        /// Successful decryptions allowed before the claw is deleted, defaults to 1
+        #[validate(range(min = 1, max = 100))]
+        pub max_reads: Option<i32>,
+        /// Time before which the claw can't be decrypted, RFC 3339
+        pub not_before: Option<String>,
+    }
+
+    /// Either `key` or at least threshold many `shares` of it must be given
     #[derive(Deserialize, ToSchema, Validate)]
     pub struct DecryptRequest {
         pub id: String,
-        pub key: String,
+        #[schema(value_type = Option<String>)]
+        pub key: Option<Zeroizing<String>>,
+        #[schema(value_type = Option<Vec<String>>)]
+        pub shares: Option<Zeroizing<Vec<String>>>,
+        #[schema(value_type = Option<String>)]
+        pub passphrase: Option<Zeroizing<String>>,
+    }
+
+    /// Payload sealed by the client
+    ///
+    /// `data` is the base64 encoding of
+    /// `"CLAW" | 0x01 | alg | nonce (12) | ciphertext | tag (16)`, where `alg` is
+    /// `0x01` for AES-256-GCM or `0x02` for ChaCha20-Poly1305 and the first 6 bytes
+    /// are the associated data. The key must never be sent to the server.
+    #[derive(Deserialize, ToSchema, Validate)]
+    pub struct SealedEncryptRequest {
+        pub data: String,
+        pub validity: ValidDuration,
+        /// Successful decryptions allowed before the claw is deleted, defaults to 1
+        #[validate(range(min = 1, max = 100))]
+        pub max_reads: Option<i32>,
+        /// Time before which the claw can't be decrypted, RFC 3339
+        #[schema(format = DateTime)]
+        pub not_before: Option<String>,
+    }
+
+    #[derive(Deserialize, ToSchema, Validate)]
+    pub struct SealedDecryptRequest {
+        pub id: String,
     }
 }
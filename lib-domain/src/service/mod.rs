@@ -1,15 +1,45 @@
+use std::sync::Arc;
+
+use lib_core::config::Config;
+use lib_core::vault::frame::Padding;
+use lib_core::vault::kek::Keks;
+use lib_core::vault::key::{KeyProvider, OsKeyProvider};
+use lib_core::vault::suite::Suite;
+
 use crate::datastore::Datastore;
 
+mod recipient;
 mod vault;
 
 pub struct Service {
     ds: Datastore,
+    keys: Arc<dyn KeyProvider + Send + Sync>,
+    keks: Arc<Keks>,
+    padding: Padding,
+    suite: Suite,
+    allowed_suites: Vec<Suite>,
 }
 
 impl Service {
     pub async fn init() -> Self {
         let ds = Datastore::init().await;
-        Self { ds }
+        let keks = Keks::from_config()
+            .unwrap_or_else(|e| panic!("Failed to load KEKs: {}", e.get_messages().0));
+        let padding = Config::get_padding()
+            .unwrap_or_else(|e| panic!("Failed to load padding: {}", e.get_messages().0));
+        let suite = Config::get_suite()
+            .unwrap_or_else(|e| panic!("Failed to load cipher suite: {}", e.get_messages().0));
+        let allowed_suites = Config::get_allowed_suites().unwrap_or_else(|e| {
+            panic!("Failed to load allowed cipher suites: {}", e.get_messages().0)
+        });
+        Self {
+            ds,
+            keys: Arc::new(OsKeyProvider),
+            keks: Arc::new(keks),
+            padding,
+            suite,
+            allowed_suites,
+        }
     }
 
     pub fn ds(&self) -> &Datastore {
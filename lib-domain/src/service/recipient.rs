@@ -0,0 +1,63 @@
+use lib_core::{AppError, AppResult, ErrType};
+
+use crate::datastore::Recipient;
+use crate::dto::recipient::{
+    req::RecipientRequest,
+    res::{RecipientResponse, StanzaResponse},
+};
+
+use super::Service;
+
+impl Service {
+    pub async fn register_recipient(&self, dto: RecipientRequest) -> AppResult<RecipientResponse> {
+        let suite = dto.suite.unwrap_or_default();
+        let public_key = suite.canonical_public_key(&dto.public_key)?;
+
+        let recipient = self.ds.save_recipient(dto.name, suite, public_key).await?;
+        Ok(recipient.into())
+    }
+
+    pub async fn get_recipient(&self, id: String) -> AppResult<RecipientResponse> {
+        let recipient = self.ds.get_recipients(&[id]).await?.pop();
+        recipient
+            .map(RecipientResponse::from)
+            .ok_or_else(|| AppError::new(ErrType::NotFound, "Recipient not found for requested ID"))
+    }
+
+    pub async fn get_stanza(
+        &self,
+        recipient_id: String,
+        claw_id: String,
+    ) -> AppResult<StanzaResponse> {
+        let stanza = self.ds.get_stanza(&claw_id, &recipient_id).await?.ok_or_else(|| {
+            AppError::new(ErrType::NotFound, "Claw is not sealed to requested recipient")
+        })?;
+        Ok(StanzaResponse { claw_id, recipient_id, stanza })
+    }
+
+    /// Seals claw `key` to each of `recipient_ids`, returns `(recipient id, stanza)`
+    pub(super) async fn seal_to(
+        &self,
+        recipient_ids: &[String],
+        key: &str,
+    ) -> AppResult<Vec<(String, String)>> {
+        let recipients = self.ds.get_recipients(recipient_ids).await?;
+        if let Some(id) = recipient_ids.iter().find(|id| !recipients.iter().any(|r| &r.id == *id)) {
+            return Err(AppError::new(ErrType::NotFound, format!("Recipient not found: {}", id)));
+        }
+
+        recipients
+            .into_iter()
+            .map(|r| {
+                let stanza = r.suite.seal(self.keys.as_ref(), &r.public_key, key.as_bytes())?;
+                Ok((r.id, stanza))
+            })
+            .collect()
+    }
+}
+
+impl From<Recipient> for RecipientResponse {
+    fn from(r: Recipient) -> Self {
+        RecipientResponse { id: r.id, name: r.name, suite: r.suite, public_key: r.public_key }
+    }
+}
@@ -1,50 +1,714 @@
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use futures::{Stream, TryStreamExt, stream};
+use http::HeaderValue;
 use lib_core::{
     AppError, AppResult, ErrType,
-    vault::{EData, Vault},
+    config::Config,
+    enums::ValidDuration,
+    vault::{
+        ClawVersion, EData, Vault, age, file::ClawFile, kek::Keks, key::KeyProvider, pgp,
+        recipient, sealed, shamir, stream::CHUNK_LEN, suite::Suite,
+    },
 };
+use mime::Mime;
 
+use crate::datastore::{Claw, Datastore, NewClaw};
 use crate::dto::vault::{
-    req::{DecryptRequest, EncryptRequest},
-    res::{DecryptResponse, EncryptResponse},
+    req::{
+        DecryptRequest, EncryptFileRequest, EncryptRequest, EncryptStreamRequest, ExportFormat,
+        ExportRequest, KeySplit, SealedDecryptRequest, SealedEncryptRequest,
+    },
+    res::{
+        ClawResponse, DecryptFileResponse, DecryptResponse, DecryptStreamResponse, EncryptResponse,
+        SealedDecryptResponse, SealedEncryptResponse,
+    },
 };
 
+use zeroize::Zeroizing;
+
 use super::Service;
 
+/// Successful reads of a claw unless requested otherwise
+const DEFAULT_MAX_READS: i32 = 1;
+
+/// Wait before retrying claws locked by readers during KEK rotation
+const REWRAP_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How the claw key is handed out after encryption
+enum KeyDelivery {
+    /// Returned as is
+    Plain,
+    /// Split into Shamir shares
+    Split(KeySplit),
+    /// Sealed to registered recipients, not returned at all
+    Recipients(Vec<String>),
+}
+
 impl Service {
-    pub async fn encrypt_data(&self, dto: EncryptRequest) -> AppResult<EncryptResponse> {
+    pub async fn encrypt_data(&self, mut dto: EncryptRequest) -> AppResult<EncryptResponse> {
+        let delivery = match (dto.split, dto.recipients) {
+            (None, None) => KeyDelivery::Plain,
+            (Some(split), None) => KeyDelivery::Split(split),
+            (None, Some(recipients)) => KeyDelivery::Recipients(recipients),
+            (Some(_), Some(_)) => {
+                return Err(AppError::new(
+                    ErrType::BadRequest,
+                    "Key can't be both split and sealed to recipients",
+                ));
+            }
+        };
+        let file = EncryptFileRequest {
+            file: Zeroizing::new(std::mem::take(&mut *dto.data).into_bytes()),
+            filename: None,
+            mime: None,
+            validity: dto.validity,
+            passphrase: dto.passphrase,
+            suite: dto.suite,
+            max_reads: dto.max_reads,
+            not_before: dto.not_before,
+        };
+
+        let Some(export) = dto.export else {
+            return self.encrypt(file, delivery).await;
+        };
+        // protections of the claw mean nothing to an export that isn't stored
+        if !export.link
+            && (!matches!(delivery, KeyDelivery::Plain)
+                || file.passphrase.is_some()
+                || file.max_reads.is_some()
+                || file.not_before.is_some())
+        {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                "Split, recipients, passphrase, max_reads and not_before require a linked export",
+            ));
+        }
+        Self::check_plaintext_enabled()?;
+        Self::check_payload_size(file.file.len())?;
+        let exported = self.export(&file.file, &export)?;
+
+        if !export.link {
+            return Ok(EncryptResponse {
+                id: None,
+                key: None,
+                shares: None,
+                export: Some(exported),
+                valid_for: None,
+                expires_at: None,
+            });
+        }
+        let res = self.encrypt(file, delivery).await?;
+        Ok(EncryptResponse { export: Some(exported), ..res })
+    }
+
+    /// Stores an age file, OpenPGP message or `.claw` file encrypted elsewhere as
+    /// claw content
+    pub async fn import_file(&self, dto: EncryptFileRequest) -> AppResult<EncryptResponse> {
+        if ClawFile::is_claw_file(&dto.file) {
+            return self.import_claw_file(dto).await;
+        }
+
+        let (filename, mime) = if age::is_age(&dto.file) {
+            ("claw.age", "application/octet-stream")
+        } else if pgp::is_message(&dto.file) {
+            ("claw.pgp", "application/pgp-encrypted")
+        } else {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                "Content is neither an age file nor an OpenPGP message",
+            ));
+        };
+
+        let filename = dto.filename.or_else(|| Some(filename.into()));
+        let dto = EncryptFileRequest { filename, mime: Some(mime.into()), ..dto };
+        self.encrypt(dto, KeyDelivery::Plain).await
+    }
+
+    /// Stores claw of a `.claw` file as is, it opens with the key it was sealed with
+    ///
+    /// The claw must not outlive the expiry of the file
+    async fn import_claw_file(&self, dto: EncryptFileRequest) -> AppResult<EncryptResponse> {
+        if dto.passphrase.is_some() || dto.suite.is_some() {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                "Passphrase and suite of a claw file are chosen when it is sealed",
+            ));
+        }
+        Self::check_payload_size(dto.file.len())?;
+
+        let file: ClawFile = std::str::from_utf8(&dto.file)
+            .map_err(|e| AppError::err(ErrType::BadRequest, e, "Claw file is not valid UTF-8"))?
+            .parse()?;
+        Self::check_file_headers(file.filename.as_deref(), file.mime.as_deref())?;
+        let suite = self.choose_suite(Some(file.suite))?;
+
         let validity = dto.validity;
+        let not_before = Self::unlock_time(dto.not_before.as_deref(), validity)?;
+        let starts_at =
+            not_before.filter(|_| Config::is_expiry_from_unlock()).unwrap_or(Utc::now());
+        let expires_at = starts_at + TimeDelta::seconds(validity.get_duration() as i64);
+        if file.expires_at.is_some_and(|at| at < expires_at) {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                "Claw file expires before the requested validity ends",
+            ));
+        }
 
-        let EData { hash, key, encrypted, e_pem } =
-            Vault::cipher().generate_hash(dto.data).encrypt()?;
+        let (kek_version, pem) = self.keks.wrap(self.keys.as_ref(), file.e_pem)?;
+        let claw = self
+            .ds
+            .save_claw(NewClaw {
+                version: file.version,
+                suite,
+                data: file.encrypted,
+                pem,
+                kdf: file.kdf,
+                filename: file.filename,
+                mime: file.mime,
+                kek_version,
+                validity,
+                max_reads: dto.max_reads.unwrap_or(DEFAULT_MAX_READS),
+                not_before,
+            })
+            .await?;
 
-        let claw = self.ds.save_claw(encrypted, e_pem, hash, validity).await?;
+        let expires_at = rfc3339(claw.expires_at());
+        Ok(EncryptResponse {
+            id: Some(claw.id),
+            key: None,
+            shares: None,
+            export: None,
+            valid_for: Some(validity.to_string()),
+            expires_at: Some(expires_at),
+        })
+    }
 
-        Ok(EncryptResponse { id: claw.id, key, valid_for: validity.to_string() })
+    pub async fn encrypt_file(&self, dto: EncryptFileRequest) -> AppResult<EncryptResponse> {
+        self.encrypt(dto, KeyDelivery::Plain).await
     }
 
     pub async fn decrypt_data(&self, dto: DecryptRequest) -> AppResult<DecryptResponse> {
-        let claw =
-            self.ds.get_claw(&dto.id).await?.ok_or_else(|| {
-                AppError::new(ErrType::NotFound, "Claw not found for requested ID")
+        self.decrypt(dto, |DecryptFileResponse { data, .. }| {
+            let data = std::str::from_utf8(&data).map_err(|e| {
+                AppError::err(
+                    ErrType::BadRequest,
+                    e,
+                    "Claw holds binary data, decrypt it as a file",
+                )
             })?;
+            Ok(DecryptResponse { data: Zeroizing::new(data.to_owned()) })
+        })
+        .await
+    }
+
+    pub async fn decrypt_file(&self, dto: DecryptRequest) -> AppResult<DecryptFileResponse> {
+        self.decrypt(dto, Ok).await
+    }
+
+    /// Encrypts `body` chunk by chunk as it arrives, see [`lib_core::vault::stream`]
+    ///
+    /// The claw is stored only once the whole body was read
+    pub async fn encrypt_stream<B: AsRef<[u8]>>(
+        &self,
+        dto: EncryptStreamRequest,
+        body: impl Stream<Item = AppResult<B>>,
+    ) -> AppResult<EncryptResponse> {
+        Self::check_plaintext_enabled()?;
+        let not_before = Self::unlock_time(dto.not_before.as_deref(), dto.validity)?;
+
+        let (EData { version, suite, key, encrypted, e_pem, kdf, .. }, mut encryptor) =
+            Vault::cipher_stream().with_passphrase(dto.passphrase).encrypt(self.keys.as_ref())?;
+
+        let (kek_version, pem) = self.keks.wrap(self.keys.as_ref(), e_pem)?;
+
+        let validity = dto.validity;
+        let mut writer = self
+            .ds
+            .begin_claw(NewClaw {
+                version,
+                suite,
+                data: encrypted,
+                pem,
+                kdf,
+                filename: dto.filename,
+                mime: dto.mime,
+                kek_version,
+                validity,
+                max_reads: dto.max_reads.unwrap_or(DEFAULT_MAX_READS),
+                not_before,
+            })
+            .await?;
+
+        let max_stream = Config::get_max_stream_bytes();
+        // never grows past its capacity, so no copy of the plaintext is left behind
+        let (mut buf, mut total) = (Zeroizing::new(Vec::with_capacity(CHUNK_LEN)), 0u64);
+        let mut body = std::pin::pin!(body);
+        while let Some(data) = body.try_next().await? {
+            let mut data = data.as_ref();
+            total += data.len() as u64;
+            if total > max_stream {
+                return Err(AppError::new(
+                    ErrType::BadRequest,
+                    format!("Data exceeds maximum size of {} bytes", max_stream),
+                ));
+            }
+
+            while !data.is_empty() {
+                // a full chunk is flushed only once more data follows, so the
+                // final chunk can be marked as last
+                if buf.len() == CHUNK_LEN {
+                    writer.write_chunk(encryptor.encrypt_chunk(&buf, false)?).await?;
+                    buf.clear();
+                }
+                let n = (CHUNK_LEN - buf.len()).min(data.len());
+                buf.extend_from_slice(&data[..n]);
+                data = &data[n..];
+            }
+        }
+        writer.write_chunk(encryptor.encrypt_chunk(&buf, true)?).await?;
+
+        let claw = writer.commit().await?;
+        let expires_at = rfc3339(claw.expires_at());
+        Ok(EncryptResponse {
+            id: Some(claw.id),
+            key: Some(key),
+            shares: None,
+            export: None,
+            valid_for: Some(validity.to_string()),
+            expires_at: Some(expires_at),
+        })
+    }
+
+    /// Decrypts a streamed claw chunk by chunk as the returned stream is polled
+    ///
+    /// A read is taken off the claw before the first chunk is sent, so a client
+    /// going away halfway has used it up all the same
+    pub async fn decrypt_stream(&self, dto: DecryptRequest) -> AppResult<DecryptStreamResponse> {
+        let (id, keys, keks) = (dto.id.clone(), self.keys.clone(), self.keks.clone());
+        let ((decryptor, filename, mime), reads_left) = self
+            .ds
+            .claim_claw(&id, move |claw| {
+                Self::check_unlocked(&claw)?;
+                if claw.version != ClawVersion::V4 {
+                    return Err(AppError::new(
+                        ErrType::BadRequest,
+                        "Claw doesn't hold streamed data, decrypt it as a file instead",
+                    ));
+                }
+
+                let decryptor = Vault::decipher(EData {
+                    version: claw.version,
+                    suite: claw.suite,
+                    hash: claw.sha256,
+                    key: Self::claw_key(dto.key, dto.shares)?,
+                    encrypted: claw.data,
+                    e_pem: keks.unwrap(claw.kek_version, claw.pem)?,
+                    kdf: claw.kdf,
+                })
+                .with_passphrase(dto.passphrase)
+                .decrypt_stream(keys.as_ref())?;
+                Ok((decryptor, claw.filename, claw.mime))
+            })
+            .await?
+            .ok_or_else(|| AppError::new(ErrType::NotFound, "Claw not found for requested ID"))?;
+
+        let used_up = (reads_left == 0).then(|| UsedUpClaw { ds: self.ds.clone(), id: id.clone() });
+        let state = (self.ds.clone(), id, decryptor, 0, used_up);
+        let stream =
+            stream::try_unfold(state, |(ds, id, mut decryptor, idx, used_up)| async move {
+                if decryptor.is_finished() {
+                    return Ok(None);
+                }
+
+                let (chunk, last) = ds
+                    .get_chunk(&id, idx)
+                    .await?
+                    .ok_or_else(|| AppError::new(ErrType::VaultError, "Stream is truncated"))?;
+                let data = decryptor.decrypt_chunk(&chunk, last)?;
+                Ok(Some((data, (ds, id, decryptor, idx + 1, used_up))))
+            });
+
+        Ok(DecryptStreamResponse { stream: Box::pin(stream), filename, mime })
+    }
+
+    async fn encrypt(
+        &self,
+        EncryptFileRequest {
+            mut file,
+            filename,
+            mime,
+            validity,
+            passphrase,
+            suite,
+            max_reads,
+            not_before,
+        }: EncryptFileRequest,
+        delivery: KeyDelivery,
+    ) -> AppResult<EncryptResponse> {
+        Self::check_plaintext_enabled()?;
+        Self::check_payload_size(file.len())?;
+        let suite = self.choose_suite(suite)?;
+        let not_before = Self::unlock_time(not_before.as_deref(), validity)?;
+
+        let EData { version, key, encrypted, e_pem, kdf, .. } =
+            Vault::cipher(std::mem::take(&mut *file))
+                .with_suite(suite)
+                .with_passphrase(passphrase)
+                .with_compression(Config::is_compression_enabled())
+                .with_padding(self.padding)
+                .encrypt(self.keys.as_ref())?;
+        let (kek_version, pem) = self.keks.wrap(self.keys.as_ref(), e_pem)?;
+
+        let (key, shares, stanzas) = match delivery {
+            KeyDelivery::Plain => (Some(key), None, vec![]),
+            KeyDelivery::Split(KeySplit { shares, threshold }) => {
+                let shares = shamir::split(self.keys.as_ref(), key.as_bytes(), threshold, shares)?;
+                (None, Some(shares), vec![])
+            }
+            KeyDelivery::Recipients(ids) => (None, None, self.seal_to(&ids, &key).await?),
+        };
+
+        let claw = NewClaw {
+            version,
+            suite,
+            data: encrypted,
+            pem,
+            kdf,
+            filename,
+            mime,
+            kek_version,
+            validity,
+            max_reads: max_reads.unwrap_or(DEFAULT_MAX_READS),
+            not_before,
+        };
+        let claw = if stanzas.is_empty() {
+            self.ds.save_claw(claw).await?
+        } else {
+            self.ds.save_claw_for(claw, stanzas).await?
+        };
+
+        let expires_at = rfc3339(claw.expires_at());
+        Ok(EncryptResponse {
+            id: Some(claw.id),
+            key,
+            shares,
+            export: None,
+            valid_for: Some(validity.to_string()),
+            expires_at: Some(expires_at),
+        })
+    }
+
+    /// Decrypts a claw, turns it into a response with `respond` and takes a
+    /// read off it, see [`crate::datastore::Datastore::consume_claw`]
+    ///
+    /// `respond` runs while the claw is locked, so the read is kept if it fails
+    async fn decrypt<T: Send + 'static>(
+        &self,
+        dto: DecryptRequest,
+        respond: impl FnOnce(DecryptFileResponse) -> AppResult<T> + Send + 'static,
+    ) -> AppResult<T> {
+        let (id, keys, keks) = (dto.id.clone(), self.keys.clone(), self.keks.clone());
+        self.ds
+            .consume_claw(&id, move |claw| {
+                Self::decrypt_claw(keys.as_ref(), &keks, claw, dto).and_then(respond)
+            })
+            .await?
+            .ok_or_else(|| AppError::new(ErrType::NotFound, "Claw not found for requested ID"))
+    }
+
+    fn decrypt_claw(
+        keys: &dyn KeyProvider,
+        keks: &Keks,
+        claw: Claw,
+        dto: DecryptRequest,
+    ) -> AppResult<DecryptFileResponse> {
+        Self::check_unlocked(&claw)?;
+        match claw.version {
+            ClawVersion::Sealed => {
+                return Err(AppError::new(
+                    ErrType::BadRequest,
+                    "Claw holds a sealed payload, fetch it as sealed instead",
+                ));
+            }
+            ClawVersion::V4 => {
+                return Err(AppError::new(
+                    ErrType::BadRequest,
+                    "Claw holds streamed data, decrypt it as a stream instead",
+                ));
+            }
+            _ => {}
+        }
 
         let vault = Vault::decipher(EData {
+            version: claw.version,
+            suite: claw.suite,
             hash: claw.sha256,
-            key: dto.key,
+            key: Self::claw_key(dto.key, dto.shares)?,
             encrypted: claw.data,
-            e_pem: claw.pem,
-        });
+            e_pem: keks.unwrap(claw.kek_version, claw.pem)?,
+            kdf: claw.kdf,
+        })
+        .with_passphrase(dto.passphrase);
+
+        let data = vault.decrypt(keys).and_then(|v| v.validate_and_get())?;
+
+        Ok(DecryptFileResponse { data, filename: claw.filename, mime: claw.mime })
+    }
+
+    pub async fn encrypt_sealed(
+        &self,
+        dto: SealedEncryptRequest,
+    ) -> AppResult<SealedEncryptResponse> {
+        let validity = dto.validity;
+        let not_before = Self::unlock_time(dto.not_before.as_deref(), validity)?;
+
+        let sealed = sealed::validate(&dto.data)?;
+        Self::check_payload_size(sealed.len())?;
 
-        let data = vault.decrypt().and_then(|v| v.validate_and_get())?;
+        let claw = self
+            .ds
+            .save_claw(NewClaw {
+                version: ClawVersion::Sealed,
+                suite: Suite::default(),
+                data: dto.data,
+                pem: String::new(),
+                kdf: None,
+                filename: None,
+                mime: None,
+                kek_version: None,
+                validity,
+                max_reads: dto.max_reads.unwrap_or(DEFAULT_MAX_READS),
+                not_before,
+            })
+            .await?;
 
-        self.ds.delete_claw(&claw.id).await?;
+        let expires_at = rfc3339(claw.expires_at());
+        Ok(SealedEncryptResponse { id: claw.id, valid_for: validity.to_string(), expires_at })
+    }
 
-        Ok(DecryptResponse { data })
+    pub async fn decrypt_sealed(
+        &self,
+        dto: SealedDecryptRequest,
+    ) -> AppResult<SealedDecryptResponse> {
+        let not_found =
+            || AppError::new(ErrType::NotFound, "Sealed claw not found for requested ID");
+        self.ds
+            .consume_claw(&dto.id, move |claw| {
+                if claw.version != ClawVersion::Sealed {
+                    return Err(not_found());
+                }
+                Self::check_unlocked(&claw)?;
+                Ok(SealedDecryptResponse { data: claw.data })
+            })
+            .await?
+            .ok_or_else(not_found)
     }
 
-    pub async fn has_claw(&self, id: String) -> AppResult<()> {
+    pub async fn has_claw(&self, id: String) -> AppResult<ClawResponse> {
         let claw = self.ds.get_claw(&id).await?;
-        claw.map(|_| ())
-            .ok_or_else(|| AppError::new(ErrType::NotFound, "Requested claw doesn't exists"))
+        claw.map(|c| ClawResponse {
+            unlocks_at: c.locked_until().map(rfc3339),
+            id: c.id,
+            passphrase_required: c.kdf.is_some(),
+            reads_left: c.reads_left,
+        })
+        .ok_or_else(|| AppError::new(ErrType::NotFound, "Requested claw doesn't exists"))
+    }
+
+    /// Re-wraps stored key material of all live claws with the active KEK
+    ///
+    /// Works in batches of `batch_size` claws, each committed on its own, so
+    /// the server keeps serving claws meanwhile. Only claws stored before the
+    /// rotation started are re-wrapped, so a server still wrapping new claws
+    /// with an older KEK can't keep it going. Claws being read are retried
+    /// until none are left. Returns the number of claws re-wrapped and the ids
+    /// of claws that failed to re-wrap.
+    pub async fn rotate_kek(&self, batch_size: i64) -> AppResult<(usize, Vec<String>)> {
+        let active = self
+            .keks
+            .active()
+            .ok_or_else(|| AppError::new(ErrType::VaultError, "No KEK is configured"))?;
+
+        let ids = self.ds.get_claws_to_rewrap(active).await?;
+        let (mut total, mut failed) = (0, vec![]);
+        loop {
+            let batch = self
+                .ds
+                .rewrap_claws(active, batch_size, &ids, &failed, |version, pem| {
+                    self.keks.rewrap(self.keys.as_ref(), version, pem)
+                })
+                .await?;
+            total += batch.rewrapped;
+            tracing::info!(
+                message = "Rewrapped claws",
+                count = batch.rewrapped,
+                total,
+                pending = batch.pending
+            );
+
+            if batch.pending == 0 {
+                failed.extend(batch.failed);
+                return Ok((total, failed));
+            }
+            // the rest is locked by readers, give them a moment
+            if batch.rewrapped == 0 && batch.failed.is_empty() {
+                tokio::time::sleep(REWRAP_RETRY_DELAY).await;
+            }
+            failed.extend(batch.failed);
+        }
     }
+
+    /// Seals `data` as requested by `export`
+    fn export(&self, data: &[u8], export: &ExportRequest) -> AppResult<String> {
+        match export.format {
+            ExportFormat::Age => {
+                let recipient = recipient::parse_public_key(&export.recipient)?;
+                age::encrypt(self.keys.as_ref(), &recipient, data)
+            }
+            ExportFormat::OpenPgp => {
+                pgp::encrypt(self.keys.as_ref(), &export.recipient, data, "claw.txt")
+            }
+        }
+    }
+
+    /// Returns claw key given either as is or as Shamir `shares`
+    fn claw_key(
+        key: Option<Zeroizing<String>>,
+        shares: Option<Zeroizing<Vec<String>>>,
+    ) -> AppResult<Zeroizing<String>> {
+        match (key, shares) {
+            (Some(key), None) => Ok(key),
+            (None, Some(shares)) => std::str::from_utf8(&shamir::combine(&shares)?)
+                .map(|key| Zeroizing::new(key.to_owned()))
+                .map_err(|e| AppError::err(ErrType::Unauthorized, e, "Shares do not match")),
+            _ => Err(AppError::new(ErrType::BadRequest, "Either key or shares must be given")),
+        }
+    }
+
+    /// Checks `filename` and `mime` of an imported claw file can be sent back
+    /// in the headers of a decrypt response
+    fn check_file_headers(filename: Option<&str>, mime: Option<&str>) -> AppResult<()> {
+        if filename.is_some_and(|f| HeaderValue::from_str(f).is_err()) {
+            return Err(AppError::new(ErrType::BadRequest, "Claw file has an invalid filename"));
+        }
+        if mime.is_some_and(|m| HeaderValue::from_str(m).is_err() || m.parse::<Mime>().is_err()) {
+            return Err(AppError::new(ErrType::BadRequest, "Claw file has an invalid mime type"));
+        }
+        Ok(())
+    }
+
+    /// Returns `requested` cipher suite if allowed by [`Config::get_allowed_suites`],
+    /// otherwise [`Config::get_suite`]
+    fn choose_suite(&self, requested: Option<Suite>) -> AppResult<Suite> {
+        let Some(suite) = requested else {
+            return Ok(self.suite);
+        };
+        if !self.allowed_suites.contains(&suite) {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                format!("Cipher suite {} is not allowed", suite),
+            ));
+        }
+        Ok(suite)
+    }
+
+    /// Parses requested `not_before` time of a claw valid for `validity`, if any
+    ///
+    /// It must lie within [`Config::get_max_lock`], so that claws can't be
+    /// parked indefinitely.
+    fn unlock_time(
+        not_before: Option<&str>,
+        validity: ValidDuration,
+    ) -> AppResult<Option<DateTime<Utc>>> {
+        let Some(not_before) = not_before else {
+            return Ok(None);
+        };
+        let at = DateTime::parse_from_rfc3339(not_before)
+            .map_err(|e| AppError::err(ErrType::BadRequest, e, "not_before must be RFC 3339"))?
+            .to_utc();
+        let now = Utc::now();
+        if at <= now {
+            return Err(AppError::new(ErrType::BadRequest, "not_before must be in the future"));
+        }
+        let max_lock = Config::get_max_lock();
+        if at > now + TimeDelta::seconds(max_lock) {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                format!("not_before must lie within {} seconds", max_lock),
+            ));
+        }
+        if !Config::is_expiry_from_unlock()
+            && at >= now + TimeDelta::seconds(validity.get_duration() as i64)
+        {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                "Claw would expire before it unlocks, not_before must lie within its validity",
+            ));
+        }
+        Ok(Some(at))
+    }
+
+    /// Refuses to decrypt `claw` before its `not_before` time
+    fn check_unlocked(claw: &Claw) -> AppResult<()> {
+        match claw.locked_until() {
+            Some(at) => Err(AppError::new(
+                ErrType::Forbidden,
+                format!("Claw is locked until {}", rfc3339(at)),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects plaintext submissions when disabled by [`Config::is_plaintext_enabled`]
+    fn check_plaintext_enabled() -> AppResult<()> {
+        if !Config::is_plaintext_enabled() {
+            return Err(AppError::new(
+                ErrType::Forbidden,
+                "Server-side encryption is disabled, submit a sealed payload instead",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validates size of data against [`Config::get_max_payload_bytes`]
+    fn check_payload_size(len: usize) -> AppResult<()> {
+        let max_payload = Config::get_max_payload_bytes();
+        if len > max_payload {
+            return Err(AppError::new(
+                ErrType::BadRequest,
+                format!("Data exceeds maximum size of {} bytes", max_payload),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Streamed claw whose last read was taken, deleted once dropped along with
+/// its stream, whether the stream finished or the client went away
+struct UsedUpClaw {
+    ds: Datastore,
+    id: String,
+}
+
+impl Drop for UsedUpClaw {
+    fn drop(&mut self) {
+        let (ds, id) = (self.ds.clone(), std::mem::take(&mut self.id));
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        handle.spawn(async move {
+            if let Err(err) = ds.delete_claw(&id).await {
+                tracing::error!(
+                    message = "Failed to delete used up claw",
+                    id = id,
+                    err = err.get_messages().0,
+                );
+            }
+        });
+    }
+}
+
+/// Formats `at` as RFC 3339 in UTC, with milliseconds as stored
+fn rfc3339(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
 }
@@ -0,0 +1,71 @@
+//! WebAssembly bindings of the vault for in-browser encryption
+//!
+//! Payloads are sealed in the envelope `/api/v1/sealed/encrypt` accepts, see
+//! [`lib_core::vault::sealed`], so plaintext and key never reach the server.
+//! Claws in the envelope of the server are opened from `.claw` files, see
+//! [`lib_core::vault::file`]. Build with `wasm-pack build lib-wasm`.
+
+use lib_core::AppError;
+use lib_core::vault::Vault;
+use lib_core::vault::file::ClawFile;
+use lib_core::vault::key::{KEY_LEN, KeyProvider, OsKeyProvider};
+use lib_core::vault::sealed::{self, SealedAlg};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+/// Generates a new random 32 byte key
+#[wasm_bindgen(js_name = generateKey)]
+pub fn generate_key() -> Result<Vec<u8>, JsError> {
+    let key = OsKeyProvider.generate_key().map_err(js_error)?;
+    Ok(key.to_vec())
+}
+
+/// Seals `data` with `key`, `alg` is `1` for AES-256-GCM or `2` for
+/// ChaCha20-Poly1305, returns base64 encoded payload
+#[wasm_bindgen]
+pub fn seal(key: &[u8], data: &[u8], alg: u8) -> Result<String, JsError> {
+    let alg = SealedAlg::try_from(alg).map_err(js_error)?;
+    sealed::seal(&OsKeyProvider, alg, &*parse_key(key)?, data).map_err(js_error)
+}
+
+/// Opens a base64 encoded payload sealed with `key`
+///
+/// Data handed to JavaScript is out of reach of [`Zeroizing`], it's moved out
+/// of the buffer rather than copied
+#[wasm_bindgen]
+pub fn open(key: &[u8], sealed: &str) -> Result<Vec<u8>, JsError> {
+    let mut data = sealed::open(&*parse_key(key)?, sealed).map_err(js_error)?;
+    Ok(std::mem::take(&mut *data))
+}
+
+/// Opens a `.claw` file with its claw `key` and `passphrase`, if it was sealed
+/// with one
+///
+/// The file holds a claw just as the server stores it, whatever its version
+/// and cipher suite
+#[wasm_bindgen(js_name = openClawFile)]
+pub fn open_claw_file(
+    file: &str,
+    key: &str,
+    passphrase: Option<String>,
+) -> Result<Vec<u8>, JsError> {
+    let file: ClawFile = file.parse().map_err(js_error)?;
+    if file.is_expired() {
+        return Err(JsError::new("Claw file has expired"));
+    }
+
+    let mut data = Vault::decipher(file.into_edata(Zeroizing::new(key.to_owned())))
+        .with_passphrase(passphrase.map(Zeroizing::new))
+        .decrypt(&OsKeyProvider)
+        .and_then(|v| v.validate_and_get())
+        .map_err(js_error)?;
+    Ok(std::mem::take(&mut *data))
+}
+
+fn parse_key(key: &[u8]) -> Result<Zeroizing<[u8; KEY_LEN]>, JsError> {
+    key.try_into().map(Zeroizing::new).map_err(|_| JsError::new("Key must be 32 bytes"))
+}
+
+fn js_error(err: AppError) -> JsError {
+    JsError::new(&err.get_messages().0)
+}
@@ -0,0 +1,165 @@
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{TimeDelta, Utc};
+use lib_core::config::Config;
+use lib_core::enums::ValidDuration;
+use lib_core::vault::Vault;
+use lib_core::vault::file::ClawFile;
+use lib_core::vault::key::OsKeyProvider;
+use lib_core::{AppError, AppResult, ErrType};
+use lib_domain::service::Service;
+use zeroize::Zeroizing;
+
+/// Default number of claws re-wrapped per transaction
+const DEFAULT_BATCH_SIZE: i64 = 100;
+
+/// Runs subcommand named by `args`, returns `false` if there is none
+///
+/// - `rotate-kek [batch size]` re-wraps all live claws with the active KEK
+/// - `seal <file> [validity]` seals `file` into `<file>.claw` and prints the
+///   claw key, the validity is given like that of a claw
+/// - `unseal <file.claw>` reads the claw key from stdin and writes the data to
+///   stdout
+///
+/// `seal` and `unseal` work offline, without a database. Both take the
+/// passphrase, if any, from `CLAW_PASSPHRASE`.
+pub async fn run(mut args: impl Iterator<Item = String>) -> bool {
+    match args.next().as_deref() {
+        Some("rotate-kek") => {
+            let batch_size = match args.next().map(|v| v.parse()) {
+                None => DEFAULT_BATCH_SIZE,
+                Some(Ok(size)) if size > 0 => size,
+                Some(_) => exit_with(
+                    AppError::new(ErrType::BadRequest, "Batch size must be a positive number"),
+                    "Invalid arguments",
+                ),
+            };
+            rotate_kek(batch_size).await;
+            true
+        }
+        Some("seal") => {
+            let Some(path) = args.next() else {
+                exit_with(
+                    AppError::new(ErrType::BadRequest, "Missing file to seal"),
+                    "Invalid arguments",
+                )
+            };
+            let validity = args
+                .next()
+                .map(|v| v.parse::<ValidDuration>())
+                .transpose()
+                .unwrap_or_else(|err| exit_with(err, "Invalid arguments"));
+            match seal(Path::new(&path), validity) {
+                Ok((out, key)) => {
+                    eprintln!("Sealed into {}", out.display());
+                    println!("{}", *key);
+                }
+                Err(err) => exit_with(err, "Sealing failed"),
+            }
+            true
+        }
+        Some("unseal") => {
+            let Some(path) = args.next() else {
+                exit_with(
+                    AppError::new(ErrType::BadRequest, "Missing file to unseal"),
+                    "Invalid arguments",
+                )
+            };
+            if let Err(err) = unseal(Path::new(&path)) {
+                exit_with(err, "Unsealing failed");
+            }
+            true
+        }
+        Some(cmd) => exit_with(
+            AppError::new(ErrType::BadRequest, format!("Unknown command: {}", cmd)),
+            "Invalid arguments",
+        ),
+        None => false,
+    }
+}
+
+async fn rotate_kek(batch_size: i64) {
+    let service = Service::init().await;
+    match service.rotate_kek(batch_size).await {
+        Ok((total, failed)) if failed.is_empty() => {
+            tracing::info!(message = "KEK rotation finished", total)
+        }
+        Ok((total, failed)) => {
+            let message = format!(
+                "Re-wrapped {} claws, {} failed: {}",
+                total,
+                failed.len(),
+                failed.join(", ")
+            );
+            exit_with(AppError::new(ErrType::VaultError, message), "KEK rotation incomplete")
+        }
+        Err(err) => exit_with(err, "KEK rotation failed"),
+    }
+}
+
+/// Seals file at `path` into a `.claw` file next to it, expiring after
+/// `validity` if given
+///
+/// Returns path of the `.claw` file and the claw key
+fn seal(path: &Path, validity: Option<ValidDuration>) -> AppResult<(PathBuf, Zeroizing<String>)> {
+    let data = std::fs::read(path)
+        .map_err(|e| AppError::err(ErrType::BadRequest, e, "Failed to read file"))?;
+
+    let edata = Vault::cipher(data)
+        .with_suite(Config::get_suite()?)
+        .with_passphrase(passphrase())
+        .with_compression(Config::is_compression_enabled())
+        .with_padding(Config::get_padding()?)
+        .encrypt(&OsKeyProvider)?;
+
+    let filename = path.file_name().map(|n| n.to_string_lossy().into_owned());
+    let expires_at = validity.map(|v| Utc::now() + TimeDelta::seconds(v.get_duration() as i64));
+    let (file, key) = ClawFile::new(edata, filename, None, expires_at)?;
+
+    let mut out = path.as_os_str().to_owned();
+    out.push(".claw");
+    std::fs::write(&out, file.to_string())
+        .map_err(|e| AppError::err(ErrType::ServerError, e, "Failed to write claw file"))?;
+    Ok((out.into(), key))
+}
+
+/// Opens `.claw` file at `path` with the claw key read from stdin and writes
+/// its data to stdout
+fn unseal(path: &Path) -> AppResult<()> {
+    let file: ClawFile = std::fs::read_to_string(path)
+        .map_err(|e| AppError::err(ErrType::BadRequest, e, "Failed to read claw file"))?
+        .parse()?;
+    if file.is_expired() {
+        return Err(AppError::new(ErrType::BadRequest, "Claw file has expired"));
+    }
+
+    // keys are far shorter, so reading never reallocates
+    let mut key = Zeroizing::new(String::with_capacity(256));
+    std::io::stdin()
+        .lock()
+        .read_line(&mut key)
+        .map_err(|e| AppError::err(ErrType::BadRequest, e, "Failed to read claw key"))?;
+    let key = Zeroizing::new(key.trim().to_string());
+
+    let data = Vault::decipher(file.into_edata(key))
+        .with_passphrase(passphrase())
+        .decrypt(&OsKeyProvider)
+        .and_then(|v| v.validate_and_get())?;
+
+    std::io::stdout()
+        .write_all(&data)
+        .map_err(|e| AppError::err(ErrType::ServerError, e, "Failed to write data"))
+}
+
+/// Returns passphrase configured via `CLAW_PASSPHRASE`, if any
+fn passphrase() -> Option<Zeroizing<String>> {
+    std::env::var("CLAW_PASSPHRASE").ok().filter(|p| !p.is_empty()).map(Zeroizing::new)
+}
+
+/// Logs `err` and exits with a failure status
+fn exit_with(err: AppError, context: &str) -> ! {
+    let (message, err_msg, at) = err.get_messages();
+    tracing::error!(message, err_msg, at, "{}", context);
+    std::process::exit(1);
+}
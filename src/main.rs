@@ -2,6 +2,7 @@ use tracing::Level;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod app;
+mod cli;
 mod routes;
 mod server;
 mod tests;
@@ -17,7 +18,10 @@ async fn main() {
     // load env
     dotenv::dotenv().ok();
 
-    // serve app
+    // run subcommand if any, serve app otherwise
+    if cli::run(std::env::args().skip(1)).await {
+        return;
+    }
     server::serve().await;
 
     tracing::info!("Server has stopped.");
@@ -5,19 +5,22 @@ use crate::app::App;
 
 pub(crate) mod fallback;
 mod health;
+mod recipient;
 mod vault;
 
 /// Function to bind routes from:
 /// - [`health`]
 /// - [`vault`]
+/// - [`recipient`]
 pub fn bind_routes(router: Router<App>) -> Router<App> {
     // root level routes
     let r = health::bind_routes();
 
     // api level routes
     let vault_routes = vault::bind_routes(Router::new());
+    let api_routes = recipient::bind_routes(vault_routes);
 
-    router.merge(r).nest("/api/v1", vault_routes)
+    router.merge(r).nest("/api/v1", api_routes)
 }
 
 #[derive(OpenApi)]
@@ -38,14 +41,43 @@ pub fn bind_routes(router: Router<App>) -> Router<App> {
             url = "https://raw.githubusercontent.com/Claw-Vault/claw-vault/refs/heads/main/LICENSE"
         ),
     ),
-    paths(health::health, vault::api::encrypt, vault::api::decrypt, vault::api::has_claw),
+    paths(
+        health::health,
+        vault::api::encrypt,
+        vault::api::decrypt,
+        vault::api::encrypt_file,
+        vault::api::decrypt_file,
+        vault::api::import_file,
+        vault::api::encrypt_stream,
+        vault::api::decrypt_stream,
+        vault::api::encrypt_sealed,
+        vault::api::decrypt_sealed,
+        vault::api::has_claw,
+        recipient::api::register,
+        recipient::api::get_recipient,
+        recipient::api::get_stanza
+    ),
     components(schemas(
         lib_core::enums::ValidDuration,
         lib_core::EmptyResponse,
+        lib_core::vault::suite::Suite,
+        lib_core::vault::recipient::RecipientSuite,
         lib_domain::dto::vault::req::EncryptRequest,
+        lib_domain::dto::vault::req::KeySplit,
+        lib_domain::dto::vault::req::ExportRequest,
+        lib_domain::dto::vault::req::ExportFormat,
         lib_domain::dto::vault::req::DecryptRequest,
+        lib_domain::dto::vault::req::EncryptFileRequest,
         lib_domain::dto::vault::res::EncryptResponse,
         lib_domain::dto::vault::res::DecryptResponse,
+        lib_domain::dto::vault::res::ClawResponse,
+        lib_domain::dto::vault::req::SealedEncryptRequest,
+        lib_domain::dto::vault::req::SealedDecryptRequest,
+        lib_domain::dto::vault::res::SealedEncryptResponse,
+        lib_domain::dto::vault::res::SealedDecryptResponse,
+        lib_domain::dto::recipient::req::RecipientRequest,
+        lib_domain::dto::recipient::res::RecipientResponse,
+        lib_domain::dto::recipient::res::StanzaResponse,
     )),
     servers()
 )]
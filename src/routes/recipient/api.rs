@@ -0,0 +1,68 @@
+use axum::Extension;
+use axum::extract::{Path, State};
+use lib_core::interceptor::ReqId;
+use lib_core::{ApiResponse, EmptyResponse, Json};
+use lib_domain::dto::recipient::req::RecipientRequest;
+use lib_domain::dto::recipient::res::{RecipientResponse, StanzaResponse};
+
+use crate::app::App;
+
+/// Api to register a recipient's public key
+#[utoipa::path(
+    post,
+    path = "/api/v1/recipient",
+    request_body = RecipientRequest,
+    responses(
+        (status=200, description="Register the given recipient", body = RecipientResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn register(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Json(dto): Json<RecipientRequest>,
+) -> ApiResponse<RecipientResponse> {
+    ApiResponse::map_res(app.service().register_recipient(dto).await, req_id)
+}
+
+/// Api to get a registered recipient
+#[utoipa::path(
+    get,
+    path = "/api/v1/recipient/{recipient-id}",
+    responses(
+        (status=200, description="Requested recipient", body = RecipientResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    params(("recipient-id" = String, Path, description = "Recipient ID")),
+    tag = "Api",
+)]
+pub async fn get_recipient(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Path(id): Path<String>,
+) -> ApiResponse<RecipientResponse> {
+    ApiResponse::map_res(app.service().get_recipient(id).await, req_id)
+}
+
+/// Api to get the claw key sealed to a recipient
+#[utoipa::path(
+    get,
+    path = "/api/v1/recipient/{recipient-id}/claw/{claw-id}",
+    responses(
+        (status=200, description="Claw key sealed to the recipient", body = StanzaResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    params(
+        ("recipient-id" = String, Path, description = "Recipient ID"),
+        ("claw-id" = String, Path, description = "Claw ID"),
+    ),
+    tag = "Api",
+)]
+pub async fn get_stanza(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Path((id, claw_id)): Path<(String, String)>,
+) -> ApiResponse<StanzaResponse> {
+    ApiResponse::map_res(app.service().get_stanza(id, claw_id).await, req_id)
+}
@@ -0,0 +1,13 @@
+use axum::Router;
+use axum::routing::{get, post};
+
+use crate::app::App;
+
+pub mod api;
+
+pub fn bind_routes(router: Router<App>) -> Router<App> {
+    router
+        .route("/recipient", post(api::register))
+        .route("/recipient/{id}", get(api::get_recipient))
+        .route("/recipient/{id}/claw/{claw_id}", get(api::get_stanza))
+}
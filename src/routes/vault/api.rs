@@ -1,9 +1,23 @@
-use axum::extract::{Path, State};
-use axum::{Extension, http::StatusCode};
+use axum::Extension;
+use axum::body::Body;
+use axum::extract::multipart::MultipartRejection;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Multipart, Path, Query, State};
+use axum::http::{HeaderMap, header};
+use axum::response::{IntoResponse, Response};
+use futures::TryStreamExt;
 use lib_core::interceptor::ReqId;
-use lib_core::{ApiResponse, EmptyResponse, Json};
-use lib_domain::dto::vault::req::{DecryptRequest, EncryptRequest};
-use lib_domain::dto::vault::res::{DecryptResponse, EncryptResponse};
+use lib_core::{ApiResponse, AppError, AppResult, EmptyResponse, ErrType, Json};
+use lib_domain::dto::vault::req::{
+    DecryptRequest, EncryptFileRequest, EncryptRequest, EncryptStreamRequest, SealedDecryptRequest,
+    SealedEncryptRequest,
+};
+use lib_domain::dto::vault::res::{
+    ClawResponse, DecryptFileResponse, DecryptResponse, DecryptStreamResponse, EncryptResponse,
+    SealedDecryptResponse, SealedEncryptResponse,
+};
+use validator::Validate;
+use zeroize::Zeroizing;
 
 use crate::app::App;
 
@@ -45,12 +59,199 @@ pub async fn decrypt(
     ApiResponse::map_res(app.service().decrypt_data(dto).await, req_id)
 }
 
+/// Api to encrypt a file
+#[utoipa::path(
+    post,
+    path = "/api/v1/encrypt/file",
+    request_body(content = EncryptFileRequest, content_type = "multipart/form-data"),
+    responses(
+        (status=200, description="Encrypt the given file", body = EncryptResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn encrypt_file(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> ApiResponse<EncryptResponse> {
+    let res = async {
+        let dto = read_file_request(multipart).await?;
+        app.service().encrypt_file(dto).await
+    };
+    ApiResponse::map_res(res.await, req_id)
+}
+
+/// Api to import an age file, OpenPGP message or `.claw` file as claw content
+///
+/// A `.claw` file is stored as is and decrypts with the key it was sealed with
+#[utoipa::path(
+    post,
+    path = "/api/v1/import",
+    request_body(content = EncryptFileRequest, content_type = "multipart/form-data"),
+    responses(
+        (status=200, description="Store the given age file, OpenPGP message or claw file", body = EncryptResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn import_file(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> ApiResponse<EncryptResponse> {
+    let res = async {
+        let dto = read_file_request(multipart).await?;
+        app.service().import_file(dto).await
+    };
+    ApiResponse::map_res(res.await, req_id)
+}
+
+/// Api to decrypt a file
+///
+/// Responds with the raw bytes of the file
+#[utoipa::path(
+    post,
+    path = "/api/v1/decrypt/file",
+    request_body = DecryptRequest,
+    responses(
+        (status=200, description="Decrypt the given file", content_type = "application/octet-stream"),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn decrypt_file(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Json(dto): Json<DecryptRequest>,
+) -> Response {
+    match app.service().decrypt_file(dto).await {
+        Ok(DecryptFileResponse { data, filename, mime }) => {
+            let mime = mime.unwrap_or_else(|| "application/octet-stream".into());
+            let disposition = content_disposition(filename.as_deref().unwrap_or("claw"));
+            (
+                [(header::CONTENT_TYPE, mime), (header::CONTENT_DISPOSITION, disposition)],
+                Body::from(into_body(data)),
+            )
+                .into_response()
+        }
+        Err(err) => ApiResponse::<()>::Err(err, req_id).into_response(),
+    }
+}
+
+/// Api to encrypt a large file as it is uploaded
+///
+/// The request body is the raw file, encrypted chunk by chunk as it arrives
+#[utoipa::path(
+    post,
+    path = "/api/v1/encrypt/stream",
+    params(
+        EncryptStreamRequest,
+        ("X-Claw-Passphrase" = Option<String>, Header, description = "Optional passphrase required in addition to the key to decrypt"),
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status=200, description="Encrypt the given file", body = EncryptResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn encrypt_stream(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    query: Result<Query<EncryptStreamRequest>, QueryRejection>,
+    headers: HeaderMap,
+    body: Body,
+) -> ApiResponse<EncryptResponse> {
+    let res = async {
+        let dto = read_stream_request(query, &headers)?;
+        let body = body
+            .into_data_stream()
+            .map_err(|e| AppError::err(ErrType::InvalidBody, e, "Failed to read file"));
+        app.service().encrypt_stream(dto, body).await
+    };
+    ApiResponse::map_res(res.await, req_id)
+}
+
+/// Api to decrypt a streamed file
+///
+/// Responds with the raw bytes of the file, decrypted chunk by chunk
+#[utoipa::path(
+    post,
+    path = "/api/v1/decrypt/stream",
+    request_body = DecryptRequest,
+    responses(
+        (status=200, description="Decrypt the given file", content_type = "application/octet-stream"),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn decrypt_stream(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Json(dto): Json<DecryptRequest>,
+) -> Response {
+    match app.service().decrypt_stream(dto).await {
+        Ok(DecryptStreamResponse { stream, filename, mime }) => {
+            let mime = mime.unwrap_or_else(|| "application/octet-stream".into());
+            let disposition = content_disposition(filename.as_deref().unwrap_or("claw"));
+            // headers are already sent, an error can only abort the response
+            let body = Body::from_stream(stream.map_ok(into_body).map_err(|e| {
+                let (message, err_msg, at) = e.get_messages();
+                tracing::error!(message, err_msg, at, "Failed to stream claw");
+                std::io::Error::other(message)
+            }));
+            ([(header::CONTENT_TYPE, mime), (header::CONTENT_DISPOSITION, disposition)], body)
+                .into_response()
+        }
+        Err(err) => ApiResponse::<()>::Err(err, req_id).into_response(),
+    }
+}
+
+/// Api to store a payload sealed by the client
+#[utoipa::path(
+    post,
+    path = "/api/v1/sealed/encrypt",
+    request_body = SealedEncryptRequest,
+    responses(
+        (status=200, description="Store the given sealed payload", body = SealedEncryptResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn encrypt_sealed(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Json(dto): Json<SealedEncryptRequest>,
+) -> ApiResponse<SealedEncryptResponse> {
+    ApiResponse::map_res(app.service().encrypt_sealed(dto).await, req_id)
+}
+
+/// Api to fetch a payload sealed by the client
+#[utoipa::path(
+    post,
+    path = "/api/v1/sealed/decrypt",
+    request_body = SealedDecryptRequest,
+    responses(
+        (status=200, description="Return the stored sealed payload", body = SealedDecryptResponse),
+        (status=400, description="Error", body = EmptyResponse),
+    ),
+    tag = "Api",
+)]
+pub async fn decrypt_sealed(
+    State(app): State<App>,
+    Extension(req_id): Extension<ReqId>,
+    Json(dto): Json<SealedDecryptRequest>,
+) -> ApiResponse<SealedDecryptResponse> {
+    ApiResponse::map_res(app.service().decrypt_sealed(dto).await, req_id)
+}
+
 /// Check if claw exists
 #[utoipa::path(
     get,
     path = "/api/v1/claw/{claw-id}",
     responses(
-        (status=200, description="Requested Claw exists", body = EmptyResponse),
+        (status=200, description="Requested Claw exists", body = ClawResponse),
         (status=400, description="Error", body = EmptyResponse),
     ),
     params(("claw-id" = String, Path, description = "Claw ID")),
@@ -60,9 +261,132 @@ pub async fn has_claw(
     State(app): State<App>,
     Extension(req_id): Extension<ReqId>,
     Path(id): Path<String>,
-) -> ApiResponse<EmptyResponse> {
-    ApiResponse::map_res(
-        app.service().has_claw(id).await.map(|_| EmptyResponse::new(StatusCode::OK, "Claw found")),
-        req_id,
-    )
+) -> ApiResponse<ClawResponse> {
+    ApiResponse::map_res(app.service().has_claw(id).await, req_id)
+}
+
+/// Reads [`EncryptFileRequest`] from multipart form
+async fn read_file_request(
+    multipart: Result<Multipart, MultipartRejection>,
+) -> AppResult<EncryptFileRequest> {
+    fn invalid(err: impl Into<Box<dyn std::error::Error>>, message: &str) -> AppError {
+        AppError::err(ErrType::InvalidBody, err, message)
+    }
+
+    let mut multipart = multipart.map_err(|e| invalid(e, "Invalid multipart form"))?;
+
+    let (mut file, mut validity, mut passphrase, mut suite, mut max_reads, mut not_before) =
+        (None, None, None, None, None, None);
+    while let Some(field) = multipart.next_field().await.map_err(|e| invalid(e, "Invalid field"))? {
+        match field.name() {
+            Some("file") => {
+                let filename = field.file_name().map(String::from);
+                let mime = field.content_type().map(String::from);
+                let data = field.bytes().await.map_err(|e| invalid(e, "Failed to read file"))?;
+                file = Some((Zeroizing::new(data.to_vec()), filename, mime));
+            }
+            Some("validity") => {
+                let text = field.text().await.map_err(|e| invalid(e, "Invalid validity"))?;
+                validity = Some(text.parse()?);
+            }
+            Some("passphrase") => {
+                let text = field.text().await.map_err(|e| invalid(e, "Invalid passphrase"))?;
+                passphrase = Some(Zeroizing::new(text));
+            }
+            Some("suite") => {
+                let text = field.text().await.map_err(|e| invalid(e, "Invalid suite"))?;
+                let value = text
+                    .parse()
+                    .map_err(|_| AppError::new(ErrType::InvalidBody, "Invalid suite"))?;
+                suite = Some(value);
+            }
+            Some("max_reads") => {
+                let text = field.text().await.map_err(|e| invalid(e, "Invalid max_reads"))?;
+                let value =
+                    text.trim().parse().map_err(|e| invalid(e, "max_reads must be a number"))?;
+                max_reads = Some(value);
+            }
+            Some("not_before") => {
+                let text = field.text().await.map_err(|e| invalid(e, "Invalid not_before"))?;
+                not_before = Some(text.trim().to_owned());
+            }
+            _ => {}
+        }
+    }
+
+    let (file, filename, mime) =
+        file.ok_or_else(|| AppError::new(ErrType::InvalidBody, "Missing field `file`"))?;
+    let validity =
+        validity.ok_or_else(|| AppError::new(ErrType::InvalidBody, "Missing field `validity`"))?;
+
+    let dto = EncryptFileRequest {
+        file,
+        filename,
+        mime,
+        validity,
+        passphrase,
+        suite,
+        max_reads,
+        not_before,
+    };
+    dto.validate().map_err(|e| {
+        let err_msg = format!("Bad Payload: {}", e);
+        AppError::err(ErrType::ValidationErr, e, err_msg)
+    })?;
+    Ok(dto)
+}
+
+/// Reads [`EncryptStreamRequest`] from query and headers
+fn read_stream_request(
+    query: Result<Query<EncryptStreamRequest>, QueryRejection>,
+    headers: &HeaderMap,
+) -> AppResult<EncryptStreamRequest> {
+    let Query(mut dto) =
+        query.map_err(|e| AppError::err(ErrType::InvalidBody, e, "Invalid query"))?;
+
+    let header = |name| {
+        headers
+            .get(name)
+            .map(|v| v.to_str().map(String::from))
+            .transpose()
+            .map_err(|e| AppError::err(ErrType::InvalidBody, e, "Invalid header"))
+    };
+    dto.mime = header(header::CONTENT_TYPE.as_str())?;
+    dto.passphrase = header("x-claw-passphrase")?.map(Zeroizing::new);
+
+    dto.validate().map_err(|e| {
+        let err_msg = format!("Bad Payload: {}", e);
+        AppError::err(ErrType::ValidationErr, e, err_msg)
+    })?;
+    Ok(dto)
+}
+
+/// Hands decrypted `data` over to the response body
+///
+/// Buffers owned by the HTTP stack are out of reach of [`Zeroizing`], the
+/// allocation is moved rather than copied so that no other copy is left behind
+fn into_body(mut data: Zeroizing<Vec<u8>>) -> Vec<u8> {
+    std::mem::take(&mut *data)
+}
+
+/// Builds `Content-Disposition` header for a download of `filename`
+///
+/// Non-ASCII names are sent percent-encoded in `filename*`, with an ASCII fallback
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
 }
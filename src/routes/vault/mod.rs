@@ -1,5 +1,7 @@
 use axum::Router;
+use axum::extract::DefaultBodyLimit;
 use axum::routing::{get, post};
+use lib_core::config::Config;
 
 use crate::app::App;
 
@@ -9,5 +11,14 @@ pub fn bind_routes(router: Router<App>) -> Router<App> {
     router
         .route("/encrypt", post(api::encrypt))
         .route("/decrypt", post(api::decrypt))
+        .route("/encrypt/file", post(api::encrypt_file))
+        .route("/decrypt/file", post(api::decrypt_file))
+        .route("/import", post(api::import_file))
+        .route("/encrypt/stream", post(api::encrypt_stream))
+        .route("/decrypt/stream", post(api::decrypt_stream))
+        .route("/sealed/encrypt", post(api::encrypt_sealed))
+        .route("/sealed/decrypt", post(api::decrypt_sealed))
         .route("/claw/{id}", get(api::has_claw))
+        // leave room for JSON escaping of the payload, size is validated by the service
+        .layer(DefaultBodyLimit::max(Config::get_max_payload_bytes() * 2))
 }
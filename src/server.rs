@@ -70,18 +70,13 @@ pub async fn serve() {
             let signal_closed = tx_signal.closed().fuse();
             pin_mut!(signal_closed);
 
-            loop {
-                tokio::select! {
-                    result = conn.as_mut() => {
-                        if let Err(_err) = result {
-                            tracing::debug!("failed to serve connection: {_err:#}");
-                        }
-                        break;
-                    }
-                    _ = &mut signal_closed => {
-                        break;
+            tokio::select! {
+                result = conn.as_mut() => {
+                    if let Err(_err) = result {
+                        tracing::debug!("failed to serve connection: {_err:#}");
                     }
                 }
+                _ = &mut signal_closed => {}
             }
         });
     }
@@ -3,6 +3,8 @@ use axum::{
     http::{Method, Request, Response, StatusCode, header},
 };
 use http_body_util::BodyExt;
+use lib_core::enums::ValidDuration;
+use lib_core::vault::{Vault, file::ClawFile, key::OsKeyProvider, recipient};
 use tower::util::ServiceExt;
 
 use crate::{app, server};
@@ -33,6 +35,34 @@ async fn encrypt_30_mins() {
         .await;
 }
 
+#[tokio::test]
+async fn encrypt_iso_duration() {
+    dotenv::dotenv().ok();
+
+    let response =
+        encrypt_req(Body::from(r#"{ "validity": "P2DT12H", "data": "random data" }"#)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(b["valid_for"], "2 days 12 hours");
+    let expires_at =
+        chrono::DateTime::parse_from_rfc3339(b["expires_at"].as_str().unwrap()).unwrap();
+    let expected = chrono::Utc::now() + chrono::TimeDelta::hours(60);
+    assert!((expected - expires_at.with_timezone(&chrono::Utc)).num_seconds().abs() < 60);
+
+    for validity in [r#"59"#, r#"864000"#, r#""P1M""#, r#""PT""#, r#""1h""#] {
+        let body = format!(r#"{{ "validity": {}, "data": "random data" }}"#, validity);
+        let response = encrypt_req(Body::from(body)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", validity);
+    }
+
+    let parse = |s: &str| s.parse::<ValidDuration>().map(|d| d.get_duration()).ok();
+    assert_eq!(parse("PT1H30M"), Some(5400));
+    assert_eq!(parse("P1W"), Some(604800));
+    assert_eq!(parse("pt90s"), Some(90));
+    assert_eq!(parse("PT30M1H"), None);
+    assert_eq!(parse("P1.5D"), None);
+}
+
 async fn encrypt_n_min(body: &'static str, expected: &'static str) {
     dotenv::dotenv().ok();
 
@@ -57,14 +87,10 @@ async fn decrypt() {
     dotenv::dotenv().ok();
 
     let er = encrypt_req(Body::from(r#"{ "validity": 60, "data": "random data" }"#)).await;
-    let eb = er.into_parts();
-    let mut eb = get_body(eb.1).await;
-    eb = eb.replace("data_id", "id");
-    eb = eb.replace("key_id", "key");
-    eb = eb.replace(r#""valid_for":"1 minute""#, "");
-    eb = eb.replace(",}", "}");
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] });
 
-    let response = decrypt_req(Body::from(eb)).await;
+    let response = decrypt_req(Body::from(body.to_string())).await;
     assert_eq!(response.status(), StatusCode::OK);
 
     let (_, b) = response.into_parts();
@@ -72,6 +98,593 @@ async fn decrypt() {
     assert!(b.contains(r#""data":"random data""#));
 }
 
+#[tokio::test]
+async fn decrypt_large_payload() {
+    dotenv::dotenv().ok();
+
+    let data = "kubeconfig: line\n".repeat(1024);
+    let body = serde_json::json!({ "validity": 60, "data": data }).to_string();
+    let er = encrypt_req(Body::from(body)).await;
+    assert_eq!(er.status(), StatusCode::OK);
+
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+
+    let response = decrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(b["data"], data);
+}
+
+#[tokio::test]
+async fn encrypt_over_max_payload() {
+    dotenv::dotenv().ok();
+
+    let data = "a".repeat(lib_core::config::Config::get_max_payload_bytes() + 1);
+    let body = serde_json::json!({ "validity": 60, "data": data }).to_string();
+    let response = encrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn decrypt_sealed() {
+    use lib_core::vault::key::{KeyProvider, OsKeyProvider};
+    use lib_core::vault::sealed::{self, SealedAlg};
+
+    dotenv::dotenv().ok();
+
+    let key = OsKeyProvider.generate_key().unwrap();
+    let data = sealed::seal(&OsKeyProvider, SealedAlg::ChaCha20Poly1305, &key, b"random data");
+    let data = data.unwrap();
+
+    let body = serde_json::json!({ "validity": 60, "data": data }).to_string();
+    let er = req(Body::from(body), "/api/v1/sealed/encrypt").await;
+    assert_eq!(er.status(), StatusCode::OK);
+
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let body = serde_json::json!({ "id": eb["id"] }).to_string();
+
+    let response = req(Body::from(body), "/api/v1/sealed/decrypt").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(b["data"], data);
+    assert_eq!(*sealed::open(&key, b["data"].as_str().unwrap()).unwrap(), b"random data");
+}
+
+#[tokio::test]
+async fn decrypt_sealed_wasm() {
+    dotenv::dotenv().ok();
+
+    // sealed in the browser through the wasm bindings
+    let key = lib_wasm::generate_key().unwrap();
+    let data = lib_wasm::seal(&key, b"random data", 1).unwrap();
+
+    let body = serde_json::json!({ "validity": 60, "data": data }).to_string();
+    let er = req(Body::from(body), "/api/v1/sealed/encrypt").await;
+    assert_eq!(er.status(), StatusCode::OK);
+
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let body = serde_json::json!({ "id": eb["id"] }).to_string();
+
+    let response = req(Body::from(body), "/api/v1/sealed/decrypt").await;
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(lib_wasm::open(&key, b["data"].as_str().unwrap()).unwrap(), b"random data");
+}
+
+#[tokio::test]
+async fn encrypt_sealed_malformed() {
+    dotenv::dotenv().ok();
+
+    let body = r#"{ "validity": 60, "data": "cmFuZG9tIGRhdGE=" }"#;
+    let response = req(Body::from(body), "/api/v1/sealed/encrypt").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn decrypt_max_reads() {
+    dotenv::dotenv().ok();
+
+    let body = r#"{ "validity": 60, "data": "random data", "max_reads": 3 }"#;
+    let er = encrypt_req(Body::from(body)).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let uri = format!("/api/v1/claw/{}", eb["id"].as_str().unwrap());
+
+    let wrong = serde_json::json!({ "id": eb["id"], "key": "d3Jvbmcga2V5" }).to_string();
+    assert_ne!(decrypt_req(Body::from(wrong)).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+    for reads_left in (0..3).rev() {
+        let response = decrypt_req(Body::from(body.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = get_req(uri.clone()).await;
+        if reads_left == 0 {
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        } else {
+            let b: serde_json::Value =
+                serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+            assert_eq!(b["reads_left"], reads_left);
+        }
+    }
+    assert_eq!(decrypt_req(Body::from(body)).await.status(), StatusCode::NOT_FOUND);
+
+    let body = r#"{ "validity": 60, "data": "random data", "max_reads": 0 }"#;
+    assert_eq!(encrypt_req(Body::from(body)).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn decrypt_concurrent() {
+    dotenv::dotenv().ok();
+
+    let er = encrypt_req(Body::from(r#"{ "validity": 60, "data": "random data" }"#)).await;
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] });
+    let winners = race_decrypts("/api/v1/decrypt", body).await;
+    assert_eq!(winners.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn decrypt_stream_concurrent() {
+    dotenv::dotenv().ok();
+
+    let file: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+    let er = encrypt_stream_req("validity=60", &file).await;
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": eb["key"], "passphrase": "correct horse" });
+    let winners = race_decrypts("/api/v1/decrypt/stream", body).await;
+    assert_eq!(winners.len(), 1);
+    for winner in winners {
+        let bytes = winner.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(bytes.as_ref(), file);
+    }
+}
+
+#[tokio::test]
+async fn decrypt_not_before() {
+    dotenv::dotenv().ok();
+
+    let not_before = chrono::Utc::now() + chrono::TimeDelta::seconds(2);
+    let body = serde_json::json!({
+        "validity": 60,
+        "data": "random data",
+        "not_before": not_before.to_rfc3339(),
+    });
+    let er = encrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let response = get_req(format!("/api/v1/claw/{}", eb["id"].as_str().unwrap())).await;
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert!(b["unlocks_at"].is_string());
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+    let response = decrypt_req(Body::from(body.clone())).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(get_body(response.into_body()).await.contains("Claw is locked until"));
+
+    tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+    let response = decrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = r#"{ "validity": 60, "data": "random data", "not_before": "2000-01-01T00:00:00Z" }"#;
+    assert_eq!(encrypt_req(Body::from(body)).await.status(), StatusCode::BAD_REQUEST);
+    // beyond MAX_LOCK_SECS
+    let body = r#"{ "validity": 60, "data": "random data", "not_before": "2100-01-01T00:00:00Z" }"#;
+    assert_eq!(encrypt_req(Body::from(body)).await.status(), StatusCode::BAD_REQUEST);
+
+    // streamed claws are time-locked through the query
+    let not_before = chrono::Utc::now() + chrono::TimeDelta::minutes(5);
+    let query = format!(
+        "validity=60&not_before={}",
+        not_before.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    );
+    let er = encrypt_stream_req(&query, b"streamed data").await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": eb["key"], "passphrase": "correct horse" });
+    let response = req(Body::from(body.to_string()), "/api/v1/decrypt/stream").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn decrypt_expired() {
+    dotenv::dotenv().ok();
+
+    let er = encrypt_req(Body::from(r#"{ "validity": 60, "data": "random data" }"#)).await;
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let id = eb["id"].as_str().unwrap();
+
+    // expire the claw without giving the cleaner a chance to run
+    let db = sqlx::PgPool::connect(&lib_core::config::Config::get_db_url()).await.unwrap();
+    sqlx::query("UPDATE claw SET expiry_at = $1 WHERE id = $2")
+        .bind(chrono::Utc::now().timestamp_millis() - 1)
+        .bind(id)
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let app = app::init().await;
+    let res = app.service().has_claw(id.into()).await;
+    assert_eq!(res.err().unwrap().get_messages().0, "Requested claw doesn't exists");
+    let count: i64 = sqlx::query_scalar("SELECT count(*) FROM claw WHERE id = $1")
+        .bind(id)
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    let body = serde_json::json!({ "id": id, "key": eb["key"] }).to_string();
+    assert_eq!(decrypt_req(Body::from(body)).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn decrypt_passphrase() {
+    dotenv::dotenv().ok();
+
+    let body = r#"{ "validity": 60, "data": "random data", "passphrase": "correct horse" }"#;
+    let er = encrypt_req(Body::from(body)).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let response = get_req(format!("/api/v1/claw/{}", eb["id"].as_str().unwrap())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(b["passphrase_required"], true);
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+    let response = decrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": eb["key"], "passphrase": "correct horse" });
+    let response = decrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let b: serde_json::Value = serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(b["data"], "random data");
+
+    let body = r#"{ "validity": 60, "data": "random data", "passphrase": "" }"#;
+    let response = encrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn decrypt_file() {
+    dotenv::dotenv().ok();
+
+    let file: &[u8] = &[0x89, b'P', b'N', b'G', 0, 0xff, 0, 0];
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        b"--boundary\r\nContent-Disposition: form-data; name=\"validity\"\r\n\r\n60\r\n",
+    );
+    body.extend_from_slice(b"--boundary\r\nContent-Disposition: form-data; name=\"file\"; filename=\"logo.png\"\r\nContent-Type: image/png\r\n\r\n");
+    body.extend_from_slice(file);
+    body.extend_from_slice(b"\r\n--boundary--\r\n");
+
+    let er = router()
+        .await
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/encrypt/file")
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=boundary")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+    // refused as text without using up the only read
+    let response = decrypt_req(Body::from(body.clone())).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = req(Body::from(body), "/api/v1/decrypt/file").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        r#"attachment; filename="logo.png"; filename*=UTF-8''logo.png"#
+    );
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(bytes.as_ref(), file);
+}
+
+#[tokio::test]
+async fn decrypt_shares() {
+    dotenv::dotenv().ok();
+
+    let body =
+        r#"{ "validity": 60, "data": "break glass", "split": { "shares": 3, "threshold": 2 } }"#;
+    let er = encrypt_req(Body::from(body)).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    assert!(eb.get("key").is_none());
+    let shares = eb["shares"].as_array().unwrap();
+    assert_eq!(shares.len(), 3);
+
+    let body = serde_json::json!({ "id": eb["id"], "shares": [shares[0]] }).to_string();
+    let response = decrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = serde_json::json!({ "id": eb["id"], "shares": [shares[2], shares[0]] }).to_string();
+    let response = decrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(get_body(response.into_body()).await.contains(r#""data":"break glass""#));
+
+    let body =
+        r#"{ "validity": 60, "data": "break glass", "split": { "shares": 2, "threshold": 3 } }"#;
+    let response = encrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn decrypt_suite() {
+    dotenv::dotenv().ok();
+
+    let body = r#"{ "validity": 60, "data": "random data", "suite": "xchacha20-poly1305" }"#;
+    let er = encrypt_req(Body::from(body)).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+    let response = decrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(get_body(response.into_body()).await.contains(r#""data":"random data""#));
+
+    let body = r#"{ "validity": 60, "data": "random data", "suite": "rot13" }"#;
+    let response = encrypt_req(Body::from(body)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn decrypt_recipient() {
+    dotenv::dotenv().ok();
+
+    let (private, public) = recipient::generate(&OsKeyProvider).unwrap();
+    let body = serde_json::json!({ "name": "admin", "public_key": recipient::encode_key(&public) });
+    let response = req(Body::from(body.to_string()), "/api/v1/recipient").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rb: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+
+    let body =
+        serde_json::json!({ "validity": 60, "data": "for admins", "recipients": ["unknown"] });
+    let response = encrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body =
+        serde_json::json!({ "validity": 60, "data": "for admins", "recipients": [rb["id"]] });
+    let er = encrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    assert!(eb.get("key").is_none());
+
+    let uri = format!(
+        "/api/v1/recipient/{}/claw/{}",
+        rb["id"].as_str().unwrap(),
+        eb["id"].as_str().unwrap()
+    );
+    let response = get_req(uri).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sb: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+
+    let key = recipient::open(&OsKeyProvider, &private, sb["stanza"].as_str().unwrap()).unwrap();
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": String::from_utf8(key.to_vec()).unwrap() });
+    let response = decrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(get_body(response.into_body()).await.contains(r#""data":"for admins""#));
+}
+
+#[tokio::test]
+async fn decrypt_hybrid_recipient() {
+    use lib_core::vault::hybrid;
+
+    dotenv::dotenv().ok();
+
+    let (private, public) = hybrid::generate(&OsKeyProvider).unwrap();
+    let body = serde_json::json!({ "name": "admin", "public_key": hybrid::encode_key(&public) });
+    let response = req(Body::from(body.to_string()), "/api/v1/recipient").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = serde_json::json!({ "name": "admin", "suite": "x25519-mlkem768", "public_key": hybrid::encode_key(&public) });
+    let response = req(Body::from(body.to_string()), "/api/v1/recipient").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rb: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert_eq!(rb["suite"], "x25519-mlkem768");
+
+    let body =
+        serde_json::json!({ "validity": 60, "data": "for admins", "recipients": [rb["id"]] });
+    let er = encrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let uri = format!(
+        "/api/v1/recipient/{}/claw/{}",
+        rb["id"].as_str().unwrap(),
+        eb["id"].as_str().unwrap()
+    );
+    let response = get_req(uri).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sb: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+
+    let key = hybrid::open(&OsKeyProvider, &private, sb["stanza"].as_str().unwrap()).unwrap();
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": String::from_utf8(key.to_vec()).unwrap() });
+    let response = decrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(get_body(response.into_body()).await.contains(r#""data":"for admins""#));
+}
+
+#[tokio::test]
+async fn export_import() {
+    dotenv::dotenv().ok();
+
+    let (_, public) = recipient::generate(&OsKeyProvider).unwrap();
+    let export = serde_json::json!({ "format": "age", "recipient": recipient::encode_key(&public), "link": false });
+    let body = serde_json::json!({ "validity": 60, "data": "for partners", "export": export });
+    let response = encrypt_req(Body::from(body.to_string())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let eb: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert!(eb.get("id").is_none());
+    let file = eb["export"].as_str().unwrap().to_string();
+
+    // nothing is stored to protect without a link
+    for (field, value) in [
+        ("split", serde_json::json!({ "shares": 3, "threshold": 2 })),
+        ("max_reads", serde_json::json!(2)),
+        ("passphrase", serde_json::json!("correct horse")),
+    ] {
+        let mut body = body.clone();
+        body[field] = value;
+        let response = encrypt_req(Body::from(body.to_string())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let response = import_req(b"not encrypted").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = import_req(file.as_bytes()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let ib: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+
+    let body = serde_json::json!({ "id": ib["id"], "key": ib["key"] }).to_string();
+    let response = req(Body::from(body), "/api/v1/decrypt/file").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        r#"attachment; filename="claw.age"; filename*=UTF-8''claw.age"#
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(bytes.as_ref(), file.as_bytes());
+}
+
+#[tokio::test]
+async fn import_claw_file() {
+    dotenv::dotenv().ok();
+
+    let edata = Vault::cipher(b"air-gapped".to_vec()).encrypt(&OsKeyProvider).unwrap();
+    let expires_at = chrono::Utc::now() + chrono::TimeDelta::hours(1);
+    let (file, key) =
+        ClawFile::new(edata, Some("notes.txt".into()), None, Some(expires_at)).unwrap();
+
+    let response = import_req(file.to_string().as_bytes()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let ib: serde_json::Value =
+        serde_json::from_str(&get_body(response.into_body()).await).unwrap();
+    assert!(ib.get("key").is_none());
+
+    let body = serde_json::json!({ "id": ib["id"], "key": *key }).to_string();
+    let response = req(Body::from(body), "/api/v1/decrypt/file").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        r#"attachment; filename="notes.txt"; filename*=UTF-8''notes.txt"#
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(bytes.as_ref(), b"air-gapped");
+
+    // a claw must not outlive the expiry of its file
+    let edata = Vault::cipher(b"air-gapped".to_vec()).encrypt(&OsKeyProvider).unwrap();
+    let expires_at = chrono::Utc::now() + chrono::TimeDelta::seconds(30);
+    let (file, _) = ClawFile::new(edata, None, None, Some(expires_at)).unwrap();
+    let response = import_req(file.to_string().as_bytes()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // headers of a file must be servable when the claw is read
+    let expires_at = chrono::Utc::now() + chrono::TimeDelta::hours(1);
+    for (filename, mime) in [(Some("notes\x01.txt"), None), (None, Some("not a mime"))] {
+        let edata = Vault::cipher(b"air-gapped".to_vec()).encrypt(&OsKeyProvider).unwrap();
+        let (file, _) = ClawFile::new(
+            edata,
+            filename.map(String::from),
+            mime.map(String::from),
+            Some(expires_at),
+        )
+        .unwrap();
+        let response = import_req(file.to_string().as_bytes()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // passphrase costs beyond bounds would tie up whoever decrypts the claw
+    let edata = Vault::cipher(b"air-gapped".to_vec()).encrypt(&OsKeyProvider).unwrap();
+    let (mut file, _) = ClawFile::new(edata, None, None, Some(expires_at)).unwrap();
+    file.kdf = Some("argon2id$v=19$m=4294967295,t=4294967295,p=1$AAAAAAAAAAAAAAAAAAAAAA".into());
+    let response = import_req(file.to_string().as_bytes()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn decrypt_stream() {
+    dotenv::dotenv().ok();
+
+    let file: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+    let er = encrypt_stream_req("validity=60&filename=dump.bin", &file).await;
+    assert_eq!(er.status(), StatusCode::OK);
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+
+    let body = serde_json::json!({ "id": eb["id"], "key": eb["key"] }).to_string();
+    let response = req(Body::from(body), "/api/v1/decrypt/stream").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": eb["key"], "passphrase": "correct horse" })
+            .to_string();
+    let response = req(Body::from(body), "/api/v1/decrypt/stream").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/octet-stream");
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(bytes.as_ref(), file);
+
+    let response = get_req(format!("/api/v1/claw/{}", eb["id"].as_str().unwrap())).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn decrypt_stream_aborted() {
+    dotenv::dotenv().ok();
+
+    let file: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+    let er = encrypt_stream_req("validity=60&max_reads=2", &file).await;
+    let eb: serde_json::Value = serde_json::from_str(&get_body(er.into_body()).await).unwrap();
+    let uri = format!("/api/v1/claw/{}", eb["id"].as_str().unwrap());
+    let body =
+        serde_json::json!({ "id": eb["id"], "key": eb["key"], "passphrase": "correct horse" })
+            .to_string();
+
+    // a download broken off before the first chunk still takes a read
+    let response = req(Body::from(body.clone()), "/api/v1/decrypt/stream").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    drop(response);
+    let b: serde_json::Value =
+        serde_json::from_str(&get_body(get_req(uri.clone()).await.into_body()).await).unwrap();
+    assert_eq!(b["reads_left"], 1);
+
+    let response = req(Body::from(body.clone()), "/api/v1/decrypt/stream").await;
+    assert_eq!(get_req(uri).await.status(), StatusCode::NOT_FOUND);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(bytes.as_ref(), file);
+
+    let response = req(Body::from(body), "/api/v1/decrypt/stream").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 async fn encrypt_req(body: Body) -> Response<Body> {
     req(body, "/api/v1/encrypt").await
 }
@@ -80,12 +693,88 @@ async fn decrypt_req(body: Body) -> Response<Body> {
     req(body, "/api/v1/decrypt").await
 }
 
-async fn req(body: Body, uri: &'static str) -> Response<Body> {
+async fn import_req(file: &[u8]) -> Response<Body> {
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        b"--boundary\r\nContent-Disposition: form-data; name=\"validity\"\r\n\r\n60\r\n",
+    );
+    body.extend_from_slice(b"--boundary\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\n");
+    body.extend_from_slice(file);
+    body.extend_from_slice(b"\r\n--boundary--\r\n");
+    router()
+        .await
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/import")
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=boundary")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+async fn encrypt_stream_req(query: &str, file: &[u8]) -> Response<Body> {
+    let frames: Vec<Result<Vec<u8>, std::io::Error>> =
+        file.chunks(50_000).map(|c| Ok(c.to_vec())).collect();
+    router()
+        .await
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/v1/encrypt/stream?{}", query))
+                .header(header::CONTENT_TYPE, "application/octet-stream")
+                .header("x-claw-passphrase", "correct horse")
+                .body(Body::from_stream(futures::stream::iter(frames)))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+/// Sends `body` to `uri` from 8 readers at once, racing 8 more with a wrong
+/// key, and returns the successful responses
+async fn race_decrypts(uri: &'static str, body: serde_json::Value) -> Vec<Response<Body>> {
+    let router = router().await;
+    let mut wrong = body.clone();
+    wrong["key"] = serde_json::json!("d3Jvbmcga2V5");
+
+    let readers: Vec<_> = (0..16)
+        .map(|i| {
+            let body = if i % 2 == 0 { &body } else { &wrong };
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            tokio::spawn(router.clone().oneshot(request))
+        })
+        .collect();
+
+    // wrong keys racing the right ones must not destroy the claw
+    let mut winners = vec![];
+    for (i, reader) in readers.into_iter().enumerate() {
+        let response = reader.await.unwrap().unwrap();
+        match i % 2 {
+            0 if response.status() == StatusCode::OK => winners.push(response),
+            0 => assert_eq!(response.status(), StatusCode::NOT_FOUND),
+            _ => assert_ne!(response.status(), StatusCode::OK),
+        }
+    }
+    winners
+}
+
+async fn router() -> axum::Router {
     let app = app::init().await;
     app.bootstrap().await;
-    let router = server::get_router(app.clone()).await;
+    server::get_router(app.clone()).await
+}
 
-    let response = router
+async fn req(body: Body, uri: &'static str) -> Response<Body> {
+    router()
+        .await
         .oneshot(
             Request::builder()
                 .method(Method::POST)
@@ -95,9 +784,15 @@ async fn req(body: Body, uri: &'static str) -> Response<Body> {
                 .unwrap(),
         )
         .await
-        .unwrap();
+        .unwrap()
+}
 
-    response
+async fn get_req(uri: String) -> Response<Body> {
+    router()
+        .await
+        .oneshot(Request::builder().method(Method::GET).uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
 }
 
 // Consumes body and prints
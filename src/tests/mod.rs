@@ -1,4 +1,6 @@
 #[cfg(test)]
 mod api;
+#[cfg(test)]
+mod vault;
 // #[cfg(test)]
 // mod test;
@@ -0,0 +1,683 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+use lib_core::AppResult;
+use lib_core::vault::file::ClawFile;
+use lib_core::vault::frame::Padding;
+use lib_core::vault::kek::Keks;
+use lib_core::vault::key::{KeyProvider, OsKeyProvider};
+use lib_core::vault::suite::Suite;
+use lib_core::vault::{ClawVersion, EData, Vault, age, pgp, recipient, shamir};
+use zeroize::Zeroizing;
+
+/// [`KeyProvider`] yielding a reproducible byte sequence
+struct CountingProvider(Cell<u8>);
+
+impl KeyProvider for CountingProvider {
+    fn fill_bytes(&self, buf: &mut [u8]) -> AppResult<()> {
+        for b in buf.iter_mut() {
+            *b = self.0.get();
+            self.0.set(self.0.get().wrapping_add(1));
+        }
+        Ok(())
+    }
+}
+
+// Claws encrypted by earlier releases, they must stay decryptable
+const V1_HASH: &str = "fa1004c468894fd1c680d82ec5b0e15ac24d67acb11ca972f6d3d7962a53dae7";
+const V1_KEY: &str = "qgsw+0TgOez+y75K16PKcwysw5zYIrJDrzEiBWKQFt5w0l9Ui+MyfBjLCP1zSs0=";
+const V1_DATA: &str = concat!(
+    "ccZDZOCjrfLu6BpxeGlcE4bQ6wCUIV0cnSZYtMt2DgNjr7aZF4u+PDxM39PD29vkqtNdHaeuYcJRJXiIvreDsvjr",
+    "twyIvbOajn2s+MScYpHpZD1IqSLYDH03+4rbrq2dqpfK1ffYo5QOuZOSkWKBBEk7EiI1WClVGF+aJxWoxAxU5aOw",
+    "eXd2CRKCEr60w47LoCbfIO+MFeUdp1eluZ8BgLmAgmaziwUYDEr2eCdyGKEp+8ecX1163vvnO+AS+MscDRnaYNat",
+    "XtfKjCr4YSDpUfinB7kUQD0mUlGVig1Atr7J4+Efy7I3CYKtVjqUCO//PfsKJYCkX/B643u9K0IXM5NwoZ7NkFC1",
+    "mhi/0nNB+13Oq/U9AwMW0NQ26s8amj6L5AaHbQSoeplvJa2nqnsFNxds/JaUbYSZHqbAlcd/USBTV/F6LF2TxZCt",
+    "s+lzqfjIFhh7D76zAwcucgsavAptHKulz3/vH76UsEYDPDAop8VIhAx7i60fOj3nZxm9Vbe5ki8tVyGuiS3lnmMP",
+    "L8PSvpYD0X7DLDanfxlGuCARPnkkjKRKyZggY8dYgtLWexMjfoqbuaXZzk0q0rR3HqWXib51nNj577Rm2ZTZoQke",
+    "GJx3G2dkMw85S4ogl/18EMfexz0iCICj7InHyrsPKmgoroMIcuOqI6XyUl1FCUQgbHk=",
+);
+const V1_PEM: &str = concat!(
+    "I76BFXqBkIrcuotnjAY38GHufRDfGz+uNVmN5/2xqbgn8Fv9oEuoeFYiHbpbfj0wqvT2aQzMeiT/I957EKfmckQI",
+    "34r2CqzxJBSL6k8Dp8EMEODDw067LPytUXjhUY0TBmvBzbZo4QEa+vnQ1mw8ZB83TU2OWqCCv0DsyhjgHqy7qKnJ",
+    "NMv9jxWz4d4U4oGr/vlQCNZakNRyRcJGXyb6vGZ2LcYgrMc9vATFm8P6E02LQlokV9QveFeyY7eXHHpV+2n0AqzB",
+    "cBtC4Lu0LDw/2gNwzCTsiatxFxAyVDAVPrXu9z03C2vw23juqYlZmMt5mKOUu90GOpp1JXvugxt6qtY4wTm9TgDA",
+    "hvlY2hpPl4T5mGUZD1UaDs9pXV2/GxS2lSinoQqBLZBY+rL6zRaE1TLnDDgpwJfqmfD6l6UxDTjZihgfWcJVVbXX",
+    "tUoQ7GANEMGySAIhjU+Dt86MXwU2okOP97ugzqZpmavMsBi2ThD8/6wNkQ2X2ySZ07Q7O8Rjm++Lkq4KQA4lHy9z",
+    "zvF4vhYgnfu6MLi4cf5fs4W86X10BytxLzspUOj11rIOe09R3KSYGhRRxwYFrYqropXvtyJBn+BInJcETVSA2bPB",
+    "7sqe5wLBouLJpwGO45fkoqaxecz4rcHiSohISSsCqbGXlqx6rKk5DFoc+jLpbFDYzDFjcsQJYfTXFbxJRrDBrJMu",
+    "MBil6h+H33QWdyy+MvmBtctm4ZORAlD02XY++EI+PMpTS1ZSgIBo9d3DweWpfGbvQzyuMAjxP2wVhN5g6Hv/uxRQ",
+    "Bg4FOir+vK85ng8MsbBZsv0kObrvUIgatNbvBSzLVK4bMMB7W9KwbqGS6P4TTMOdacEKwfG+2f9SJDiLCpSs7060",
+    "z+twY/TAla4KFFkdSTDxU+7LkhcTnHjybS/Wwewg7Mv1mUndzjJodX7qRbjmKPUkBG4kJCVl9BEUys5q1RDY2Tv3",
+    "Uh1WSdW9suynw+o8zLp7Nyv+nX3aZdGD4WtBeYPdTdblG4g7u/u+cTAFdOhO3+kQPVogSvvd2azKvDWVH6HjF5tS",
+    "A1BezTs+OYUgXzi4C2M/+RT7AP8A/wD/",
+);
+
+const V2_KEY: &str = "fFT5Yx2dISEOFzTsSDDBOYyswUSFbDe7YCMPmJ0htDfxD9O0c2fvYEi5SsBeBfo=";
+const V2_DATA: &str = concat!(
+    "TUJ6WYINK2A8oBz2IJvWUeMVbgiLDajqNkhyLcTXitHIFPNIuyHH7t9/nyQ/I9ME+GVW39eNGvdIKc2BNAplwbGp",
+    "vfEbhMVEI0H8ktMOrVm7SgQD9wUSq65S7/iDNnVKAd6j9vftLZo8KbWHqSp5WHZt7gpr7GTE9QlGF5xxQ7F9nDoq",
+    "2SC8CljpZO5IaDWgC/Du+IpM09TCtrHEmyuK7oBL3ptoyQoZiLtYF/fteQA1Op6umFUoOpPjBFZRsqviPx4kL1cu",
+    "BubLYzEvaamS6upaWQauns6cPioby0hYxM6bU7hhiE8dPyj+dWjZRtE9nCRo7hmTwo15EpVe1cYDbMP+xC+aHiTe",
+    "/ZCet8Gow3PvxTM8LomeYYU16mKB/DX1KNHRy1fPAqjz/sM8UoRCfbV6SZe2w8nzUQOo1Yokf0ic99mqJgr+QAte",
+    "eIIa86krOqq2dIbxU7Y7Y+DSFclZJz/QtPoDE2D1UAxMdympS29YAlzzSDl8I3IGIRBdNo/0WNQ0lBWejmR+gHJH",
+    "5Wm4SF5c2+QFAAe57qgJ153mR0qUYlRneYoxV5TQkqxlTTNtGbQjv8IOhFrL+h8aKONx9qriXoWHpOZza7x21TL1",
+    "FzF+Pfl4k/DhAD94fdlfGTEuWyTWyp7K/HXiZP1Xe3T0opCh4nRWfTEImlf6FxjZOheYNT6GsvPP+yB+apIvS/yf",
+    "tJppvZvvLJf2UxPUk6fZ7fG1mUkh1QyslCUPTA==",
+);
+const V2_PEM: &str = concat!(
+    "/vd7G08yPbHezYkywR1JEC1FUWCjR2HqehIHu1m36OI8GhDLl0IxMlblHa9b6T1lBn6eZPOnSPSkekga0fkIE43k",
+    "jtBKbBmIhh7nEu4pCLY45a+nITFVsx+rNcQARI/IBtfdXWwR0FFxeJh4Jb4X8JAsYpFuRsBpOeOs67r6pTKgqka+",
+    "Sl2Y29b721I2Yko9DIZCoPhFJLgcPnUx3/3E2BzSipha9N11SIr39kfrDpNwri2xyFDj7fzhEuBgNPcyJdbc/0YR",
+    "FJvSs1y3XASaxMPXFU1CNThWG/04D8gZKJC/cZH4GOyWP2Xq2AkVGMTSrAGiItwqj3eFEo/TNa8pSH4nQN8ZxgDr",
+    "VdQXji5QfJOShRYsJ+CKWFVHP7d9gCK1fDVwqAW7PrdXNwzUOEeU7zLwv0IbySs2N1il2R09ynz3p4Ekf7bIOljy",
+    "vOsMMkWO8YGspHa2p0QuWfH1OFnxjFk8r/Z9aD2WF4CCNBNRF788C+y+bksUa90BHUArBX3Hr3ms1rF6Old/4iIm",
+    "48EVoX0bAgdZ8+h0XEdyAacYIZ9yxUr+aqg83iEc3Xfk4x3r9vhp6eTrWm+5su8hPBZuhfAWgiHtEB1+fr+pOGBp",
+    "VPtuflXHMA7J5zGw7J9JsrIlEu6+gHEvA6KyKHPvb3DFLD6xyvbM6JUxwfAy2Ch/ji2wJcpkJA05miloFUXs00bs",
+    "S0JGv50v5O44DN9VGzWoRU4j9wckSx/5QrcLY5lzxyrYaiYUINOm0wBJ/cs1+wHXtekuYzwM762Go3PGQQlPX3hG",
+    "fPWp3S/b6urAZhzZ9PMGA71kMzaiKNxYwWnbIPnd9zz95idnX2EJ61JmWblmQBNLBiVIehoNkUhD2nGdixchWXoz",
+    "eNUW0mgaBItg+m7TVtQb92uzpE4D/rsVaQJIiWf0gd9cpapM1kPA0jwt/tQt464HCudnS8K3ySlkKZf0fpHnYBvC",
+    "4NUd4Kq3Lf08uSBr4DMQg3d0P+CbuWyIAwiqvUl6TPw64K71rRvAxjxXHN52DqbTEd5LuGU0Tgb5VAriQDbngXDY",
+    "Jy5KbQgFS2IAHz4bDmw/+RT7AP8A/wD/",
+);
+
+// X25519 key pair with private key 0x01..=0x20, encoded by an independent bech32 implementation
+const AGE_RECIPIENT: &str = "age1q73he0q5yzfu3d64msd3p6rvksnrwjk3d2598mgtmlqt9wrdr37q2vrn72";
+const AGE_IDENTITY: &str =
+    "AGE-SECRET-KEY-1QYPQXPQ9QCRSSZG2PVXQ6RS0ZQG3YYC5Z5TPWXQERGD3C8G7RUSQGPQYEE";
+const AGE_PUBLIC_KEY: &str = "B6N8vBQgk8i3VdwbEOhstCY3StFqqFPtC9/AsrhtHHw=";
+
+// GnuPG key with an ed25519 primary key and a cv25519 encryption subkey
+const PGP_PUBLIC_KEY: &str = concat!(
+    "-----BEGIN PGP PUBLIC KEY BLOCK-----\n",
+    "\n",
+    "mDMEatSfZBYJKwYBBAHaRw8BAQdAoje2yQ/Fd+opRtTgm9TqXYgDCtGezGJjEWzP\n",
+    "HqQ6IyG0E2N2IDxjdkBleGFtcGxlLmNvbT6IkAQTFggAOBYhBBxPIKvjlXy7Plej\n",
+    "+XrOwQwkocKJBQJq1J9kAhsDBQsJCAcCBhUKCQgLAgQWAgMBAh4BAheAAAoJEHrO\n",
+    "wQwkocKJllQA/3/Z4K2Rad5KH1fzMiatvBuih/Wat/7T0VXAdPka/HVDAQCm+M29\n",
+    "QX8BX3bMrGIXrvQyDA65izeBT4z/RCRCE/3CALg4BGrUn2QSCisGAQQBl1UBBQEB\n",
+    "B0DOFM3+AvV0o8fvKQcgRaWXfB35WAB5V23zxrPxENdTUQMBCAeIeAQYFggAIBYh\n",
+    "BBxPIKvjlXy7Plej+XrOwQwkocKJBQJq1J9kAhsMAAoJEHrOwQwkocKJxzEA/iNn\n",
+    "QrGGb6W2/7yumXAvOUEyV9knLvMVEmcB0DQEkarqAPwM6PPnan9sHa9d8WmSyWo4\n",
+    "TS6GsnKAHWl36anbzVp3BA==\n",
+    "=JeaJ\n",
+    "-----END PGP PUBLIC KEY BLOCK-----\n",
+);
+
+fn decrypt(version: ClawVersion, hash: Option<&str>, key: &str, data: &str, pem: &str) -> Vec<u8> {
+    let vault = Vault::decipher(EData {
+        version,
+        suite: Suite::default(),
+        hash: hash.map(String::from),
+        key: Zeroizing::new(key.into()),
+        encrypted: data.into(),
+        e_pem: pem.into(),
+        kdf: None,
+    });
+    vault.decrypt(&OsKeyProvider).and_then(|v| v.validate_and_get()).unwrap().to_vec()
+}
+
+#[test]
+fn decrypt_v1() {
+    assert_eq!(
+        decrypt(ClawVersion::V1, Some(V1_HASH), V1_KEY, V1_DATA, V1_PEM),
+        b"legacy claw data"
+    );
+}
+
+#[test]
+fn decrypt_v1_checksum() {
+    let decrypt = |hash: Option<&str>| {
+        Vault::decipher(EData {
+            version: ClawVersion::V1,
+            suite: Suite::default(),
+            hash: hash.map(String::from),
+            key: Zeroizing::new(V1_KEY.into()),
+            encrypted: V1_DATA.into(),
+            e_pem: V1_PEM.into(),
+            kdf: None,
+        })
+        .decrypt(&OsKeyProvider)
+        .and_then(|v| v.validate_and_get())
+    };
+
+    let mut tampered = V1_HASH.to_string();
+    tampered.replace_range(..1, "0");
+    assert!(decrypt(Some(&tampered)).is_err());
+    assert!(decrypt(None).is_err());
+}
+
+#[test]
+fn decrypt_v2() {
+    assert_eq!(decrypt(ClawVersion::V2, None, V2_KEY, V2_DATA, V2_PEM), b"legacy claw data");
+}
+
+#[test]
+fn decrypt_wrong_version() {
+    let vault = Vault::decipher(EData {
+        version: ClawVersion::V2,
+        suite: Suite::default(),
+        hash: Some(V1_HASH.into()),
+        key: Zeroizing::new(V1_KEY.into()),
+        encrypted: V1_DATA.into(),
+        e_pem: V1_PEM.into(),
+        kdf: None,
+    });
+    assert!(vault.decrypt(&OsKeyProvider).is_err());
+}
+
+#[test]
+fn encrypt_current_version() {
+    let edata = Vault::cipher("random data".into()).encrypt(&OsKeyProvider).unwrap();
+    assert_eq!(edata.version, ClawVersion::CURRENT);
+    assert!(edata.hash.is_none());
+
+    let data =
+        Vault::decipher(edata).decrypt(&OsKeyProvider).and_then(|v| v.validate_and_get()).unwrap();
+    assert_eq!(*data, b"random data");
+}
+
+#[test]
+fn encrypt_deterministic_provider() {
+    let encrypt = || {
+        let provider = CountingProvider(Cell::new(0));
+        Vault::cipher("random data".into()).encrypt(&provider).unwrap()
+    };
+    let (a, b) = (encrypt(), encrypt());
+    assert_eq!(a.key, b.key);
+    assert_eq!(a.encrypted, b.encrypted);
+    assert_eq!(a.e_pem, b.e_pem);
+
+    let data = Vault::decipher(a)
+        .decrypt(&CountingProvider(Cell::new(0)))
+        .and_then(|v| v.validate_and_get())
+        .unwrap();
+    assert_eq!(*data, b"random data");
+}
+
+#[test]
+fn encrypt_known_answer() {
+    // pinned so that every crypto backend keeps producing the same claws
+    let edata =
+        Vault::cipher("random data".into()).encrypt(&CountingProvider(Cell::new(0))).unwrap();
+    assert_eq!(edata.encrypted, "ICEiIyQlJicoKSor0kjHHgj3dy5+HTavQB+zbCAzAwpmLJU4gYWpMg==");
+    assert_eq!(
+        edata.e_pem,
+        "TE1OT1BRUlNUVVZXCOOQYBod8H+Q7SJ0sAoB24KStlV1gWIeS3lT4F9JfmiTxWXRAVyW6IBzI3mZ31LV"
+    );
+}
+
+#[cfg(all(feature = "openssl", feature = "rustcrypto"))]
+#[test]
+fn backends_agree() {
+    use lib_core::vault::backend::{Aead, Digest, openssl as ossl, rustcrypto as rc};
+
+    let (key, nonce, data) = ([7; 32], [9; 12], b"random data".as_slice());
+    for aead in [Aead::Aes256Gcm, Aead::ChaCha20Poly1305] {
+        let encrypted = ossl::aead_encrypt(aead, &key, &nonce, b"aad", data).unwrap();
+        assert_eq!(encrypted, rc::aead_encrypt(aead, &key, &nonce, b"aad", data).unwrap());
+        assert_eq!(rc::aead_decrypt(aead, &key, &nonce, b"aad", &encrypted).unwrap(), data);
+        assert!(rc::aead_decrypt(aead, &key, &nonce, b"", &encrypted).is_err());
+    }
+    for digest in [Digest::Sha256, Digest::Sha384, Digest::Sha512] {
+        assert_eq!(ossl::digest(digest, data), rc::digest(digest, data));
+    }
+    assert_eq!(ossl::sha1(data), rc::sha1(data));
+    assert_eq!(ossl::hmac_sha256(&key, data).unwrap(), rc::hmac_sha256(&key, data).unwrap());
+    for salt in [&[][..], &nonce] {
+        assert_eq!(
+            ossl::hkdf_sha256(&key, salt, b"info").unwrap(),
+            rc::hkdf_sha256(&key, salt, b"info").unwrap()
+        );
+    }
+    assert_eq!(
+        ossl::argon2id(data, &nonce, 2, 1, 64).unwrap(),
+        rc::argon2id(data, &nonce, 2, 1, 64).unwrap()
+    );
+
+    let public = ossl::x25519_public_key(&key).unwrap();
+    assert_eq!(public, rc::x25519_public_key(&key).unwrap());
+    assert_eq!(ossl::x25519(&[3; 32], &public).unwrap(), rc::x25519(&[3; 32], &public).unwrap());
+
+    for kek_len in [16, 24, 32] {
+        assert_eq!(
+            ossl::aes_key_wrap(&key[..kek_len], &[5; 40]).unwrap(),
+            rc::aes_key_wrap(&key[..kek_len], &[5; 40]).unwrap()
+        );
+    }
+    assert_eq!(
+        ossl::aes_256_cfb_encrypt(&key, &[0; 16], data).unwrap(),
+        rc::aes_256_cfb_encrypt(&key, &[0; 16], data).unwrap()
+    );
+    assert!(rc::eq(data, data) && !rc::eq(data, b"random datA") && !rc::eq(data, b"random"));
+}
+
+#[test]
+fn decrypt_wrong_key() {
+    let encrypt = || Vault::cipher("random data".into()).encrypt(&OsKeyProvider).unwrap();
+    let (mut edata, other) = (encrypt(), encrypt());
+    edata.key = other.key;
+    assert!(Vault::decipher(edata).decrypt(&OsKeyProvider).is_err());
+}
+
+#[test]
+fn decrypt_passphrase() {
+    let encrypt = || {
+        Vault::cipher("random data".into())
+            .with_passphrase(Some(Zeroizing::new("correct horse".into())))
+            .encrypt(&OsKeyProvider)
+            .unwrap()
+    };
+    let decrypt = |edata: EData, passphrase: Option<&str>| {
+        Vault::decipher(edata)
+            .with_passphrase(passphrase.map(|p| Zeroizing::new(p.into())))
+            .decrypt(&OsKeyProvider)
+            .and_then(|v| v.validate_and_get())
+    };
+
+    let edata = encrypt();
+    assert!(edata.kdf.is_some());
+    assert_eq!(*decrypt(edata, Some("correct horse")).unwrap(), b"random data");
+
+    assert!(decrypt(encrypt(), Some("battery staple")).is_err());
+    assert!(decrypt(encrypt(), None).is_err());
+
+    // parameters beyond bounds are refused before any stretching
+    let salt = "AAAAAAAAAAAAAAAAAAAAAA";
+    for kdf in [
+        format!("argon2id$v=19$m=4294967295,t=2,p=1${salt}"),
+        format!("argon2id$v=19$m=19456,t=4294967295,p=1${salt}"),
+        format!("argon2id$v=19$m=19456,t=2,p=16${salt}"),
+        format!("argon2id$v=19$m=19456,t=2,p=0${salt}"),
+        "argon2id$v=19$m=19456,t=2,p=1$AAAA".into(),
+    ] {
+        let edata = EData { kdf: Some(kdf), ..encrypt() };
+        let err = decrypt(edata, Some("correct horse")).unwrap_err();
+        assert_eq!(err.get_messages().0, "Passphrase parameters are out of bounds");
+    }
+}
+
+#[test]
+fn encrypt_binary() {
+    let data = vec![0, 159, 146, 150, 0, 255, 0, 0];
+    let edata = Vault::cipher(data.clone()).encrypt(&OsKeyProvider).unwrap();
+
+    let decrypted =
+        Vault::decipher(edata).decrypt(&OsKeyProvider).and_then(|v| v.validate_and_get()).unwrap();
+    assert_eq!(*decrypted, data);
+}
+
+#[test]
+fn encrypt_suites() {
+    for suite in Suite::ALL {
+        let encrypt = || {
+            Vault::cipher("random data".into()).with_suite(suite).encrypt(&OsKeyProvider).unwrap()
+        };
+        let decrypt = |edata| {
+            Vault::decipher(edata).decrypt(&OsKeyProvider).and_then(|v| v.validate_and_get())
+        };
+
+        let edata = encrypt();
+        assert_eq!(edata.suite, suite);
+        assert_eq!(*decrypt(edata).unwrap(), b"random data");
+
+        // claws only decrypt with the suite they were encrypted with
+        let other = Suite::ALL.into_iter().find(|s| *s != suite).unwrap();
+        assert!(decrypt(EData { suite: other, ..encrypt() }).is_err());
+
+        assert_eq!(suite.to_string().parse::<Suite>().unwrap(), suite);
+    }
+}
+
+#[test]
+fn encrypt_compressed() {
+    let data = "log line repeated over and over\n".repeat(1000).into_bytes();
+    let encrypt = |compress| {
+        Vault::cipher(data.clone()).with_compression(compress).encrypt(&OsKeyProvider).unwrap()
+    };
+    let (plain, compressed) = (encrypt(false), encrypt(true));
+    assert!(compressed.encrypted.len() < plain.encrypted.len() / 10);
+
+    let decrypt = |edata, max_size| {
+        Vault::decipher(edata)
+            .with_max_size(max_size)
+            .decrypt(&OsKeyProvider)
+            .and_then(|v| v.validate_and_get())
+    };
+    assert_eq!(*decrypt(compressed, data.len()).unwrap(), data);
+    assert_eq!(*decrypt(plain, 1).unwrap(), data);
+
+    // decompressing beyond the limit is refused
+    assert!(decrypt(encrypt(true), data.len() - 1).is_err());
+
+    // incompressible data is stored as is
+    let edata = Vault::cipher(vec![7]).with_compression(true).encrypt(&OsKeyProvider).unwrap();
+    assert_eq!(*decrypt(edata, 1).unwrap(), [7]);
+}
+
+#[test]
+fn encrypt_padded() {
+    let encrypt = |data: &[u8], padding| {
+        Vault::cipher(data.to_vec()).with_padding(padding).encrypt(&OsKeyProvider).unwrap()
+    };
+    let decrypt = |edata| {
+        Vault::decipher(edata).decrypt(&OsKeyProvider).and_then(|v| v.validate_and_get()).unwrap()
+    };
+
+    // trailing zeros and 0x80 bytes survive padding
+    for data in [&b""[..], b"1234", &[1, 0x80, 0], &[0; 100], &[0x80; 1023]] {
+        for padding in [Padding::PowerOfTwo, Padding::Step(1024)] {
+            assert_eq!(*decrypt(encrypt(data, padding)), data);
+        }
+    }
+
+    let len = |data: &[u8], padding| encrypt(data, padding).encrypted.len();
+    assert_eq!(len(b"1234", Padding::Step(1024)), len(&[1; 1000], Padding::Step(1024)));
+    assert_eq!(len(&[1; 100], Padding::PowerOfTwo), len(&[1; 120], Padding::PowerOfTwo));
+    assert_ne!(len(&[1; 100], Padding::None), len(&[1; 120], Padding::None));
+
+    assert_eq!("pow2".parse::<Padding>().unwrap(), Padding::PowerOfTwo);
+    assert_eq!("1024".parse::<Padding>().unwrap(), Padding::Step(1024));
+    assert!("0".parse::<Padding>().is_err());
+}
+
+#[test]
+fn encrypt_stream() {
+    let (edata, mut encryptor) = Vault::cipher_stream().encrypt(&OsKeyProvider).unwrap();
+    let chunks: Vec<Vec<u8>> = ["first", "second", "third"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| encryptor.encrypt_chunk(c.as_bytes(), i == 2).unwrap())
+        .collect();
+    assert!(encryptor.encrypt_chunk(b"more", true).is_err());
+
+    let decryptor = || {
+        Vault::decipher(EData {
+            version: edata.version,
+            suite: edata.suite,
+            hash: edata.hash.clone(),
+            key: edata.key.clone(),
+            encrypted: edata.encrypted.clone(),
+            e_pem: edata.e_pem.clone(),
+            kdf: edata.kdf.clone(),
+        })
+        .decrypt_stream(&OsKeyProvider)
+        .unwrap()
+    };
+
+    let mut dec = decryptor();
+    let decrypted: Vec<Vec<u8>> = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, c)| dec.decrypt_chunk(c, i == 2).unwrap().to_vec())
+        .collect();
+    assert_eq!(decrypted, [b"first".to_vec(), b"second".to_vec(), b"third".to_vec()]);
+    assert!(dec.is_finished());
+
+    // truncated stream, second chunk claimed to be the last one
+    let mut dec = decryptor();
+    dec.decrypt_chunk(&chunks[0], false).unwrap();
+    assert!(dec.decrypt_chunk(&chunks[1], true).is_err());
+
+    // reordered chunks
+    let mut dec = decryptor();
+    assert!(dec.decrypt_chunk(&chunks[1], false).is_err());
+
+    // streamed claws can't be decrypted at once
+    assert!(Vault::decipher(edata).decrypt(&OsKeyProvider).is_err());
+}
+
+#[test]
+fn kek_rotation() {
+    let old = Keks::new(Some(1), BTreeMap::from([(1, [1; 32])])).unwrap();
+    let both = Keks::new(Some(2), BTreeMap::from([(1, [1; 32]), (2, [2; 32])])).unwrap();
+    let new = Keks::new(Some(2), BTreeMap::from([(2, [2; 32])])).unwrap();
+
+    let (version, wrapped) = old.wrap(&OsKeyProvider, "pem".into()).unwrap();
+    assert_eq!(version, Some(1));
+    assert_ne!(wrapped, "pem");
+    assert!(new.unwrap(version, wrapped.clone()).is_err());
+
+    let (version, wrapped) = both.rewrap(&OsKeyProvider, version, wrapped).unwrap();
+    assert_eq!(version, Some(2));
+    assert_eq!(new.unwrap(version, wrapped).unwrap(), "pem");
+
+    // claws stored before a KEK was configured
+    let (version, wrapped) = new.rewrap(&OsKeyProvider, None, "pem".into()).unwrap();
+    assert_eq!(new.unwrap(version, wrapped).unwrap(), "pem");
+
+    let none = Keks::new(None, BTreeMap::new()).unwrap();
+    assert_eq!(none.wrap(&OsKeyProvider, "pem".into()).unwrap(), (None, "pem".into()));
+    assert!(Keks::new(Some(3), BTreeMap::from([(2, [2; 32])])).is_err());
+}
+
+#[test]
+fn shamir_split() {
+    let secret = b"claw key";
+    let shares = shamir::split(&OsKeyProvider, secret, 2, 3).unwrap();
+    assert_eq!(shares.len(), 3);
+
+    for (i, j) in [(0, 1), (0, 2), (2, 1)] {
+        let pair = [shares[i].clone(), shares[j].clone()];
+        assert_eq!(*shamir::combine(&pair).unwrap(), secret);
+    }
+    assert_eq!(*shamir::combine(&shares).unwrap(), secret);
+
+    let shares = shamir::split(&OsKeyProvider, secret, 3, 5).unwrap();
+    assert_ne!(*shamir::combine(&shares[..2]).unwrap(), secret);
+    assert_eq!(*shamir::combine(&shares[2..]).unwrap(), secret);
+
+    assert!(shamir::combine(&shares[..1]).is_err());
+    assert!(shamir::combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+    assert!(shamir::split(&OsKeyProvider, secret, 4, 3).is_err());
+}
+
+#[test]
+fn recipient_seal() {
+    let private = recipient::parse_private_key(AGE_IDENTITY).unwrap();
+    let public = recipient::parse_public_key(AGE_RECIPIENT).unwrap();
+    assert_eq!(*private, core::array::from_fn(|i| i as u8 + 1));
+    assert_eq!(public, recipient::parse_public_key(AGE_PUBLIC_KEY).unwrap());
+    assert_eq!(recipient::public_key(&private).unwrap(), public);
+    assert!(recipient::parse_public_key(&AGE_RECIPIENT.replace("72", "27")).is_err());
+
+    let stanza = recipient::seal(&OsKeyProvider, &public, b"claw key").unwrap();
+    assert_eq!(*recipient::open(&OsKeyProvider, &private, &stanza).unwrap(), b"claw key");
+
+    let (other, _) = recipient::generate(&OsKeyProvider).unwrap();
+    assert!(recipient::open(&OsKeyProvider, &other, &stanza).is_err());
+}
+
+#[test]
+fn export_age_pgp() {
+    let public = recipient::parse_public_key(AGE_RECIPIENT).unwrap();
+    let file = age::encrypt(&OsKeyProvider, &public, b"random data").unwrap();
+    assert!(file.starts_with("-----BEGIN AGE ENCRYPTED FILE-----\n"));
+    assert!(age::is_age(file.as_bytes()));
+
+    let message = pgp::encrypt(&OsKeyProvider, PGP_PUBLIC_KEY, b"random data", "claw.txt").unwrap();
+    assert!(message.starts_with("-----BEGIN PGP MESSAGE-----\n\n"));
+    assert!(message.ends_with("-----END PGP MESSAGE-----\n"));
+    assert!(pgp::is_message(message.as_bytes()));
+    assert!(!pgp::is_message(file.as_bytes()));
+
+    assert!(pgp::encrypt(&OsKeyProvider, AGE_RECIPIENT, b"random data", "claw.txt").is_err());
+}
+
+#[test]
+fn export_pgp_gpg() {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let home = std::env::temp_dir().join(format!("claw-gpg-{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let gpg = |args: &[&str], input: &[u8]| {
+        let mut child = Command::new("gpg")
+            .arg("--homedir")
+            .arg(&home)
+            .args(["--batch", "--quiet"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("gpg must be installed");
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    // secret keys hold only the subkey messages must be encrypted to
+    for (public, secret) in [
+        (include_str!("vectors/pgp-ed25519.asc"), include_str!("vectors/pgp-ed25519-secret.asc")),
+        (include_str!("vectors/pgp-rsa.asc"), include_str!("vectors/pgp-rsa-secret.asc")),
+    ] {
+        assert!(gpg(&["--import"], secret.as_bytes()).status.success());
+        let message = pgp::encrypt(&OsKeyProvider, public, b"random data", "claw.txt").unwrap();
+        let output = gpg(&["--decrypt"], message.as_bytes());
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(output.stdout, b"random data");
+    }
+
+    // primary key expired, or its user ID certification tampered with
+    for public in [
+        include_str!("vectors/pgp-expired.asc"),
+        include_str!("vectors/pgp-forged-ed25519.asc"),
+        include_str!("vectors/pgp-forged-rsa.asc"),
+    ] {
+        assert!(pgp::encrypt(&OsKeyProvider, public, b"random data", "claw.txt").is_err());
+    }
+
+    Command::new("gpgconf").arg("--homedir").arg(&home).args(["--kill", "gpg-agent"]).status().ok();
+    std::fs::remove_dir_all(&home).ok();
+}
+
+#[test]
+fn claw_file() {
+    let edata = Vault::cipher("random data".into())
+        .with_passphrase(Some(Zeroizing::new("correct horse".into())))
+        .encrypt(&OsKeyProvider)
+        .unwrap();
+    let (file, key) = ClawFile::new(edata, Some("notes.txt".into()), None, None).unwrap();
+    let armored = file.to_string();
+    assert!(ClawFile::is_claw_file(armored.as_bytes()));
+    assert!(!armored.contains(key.as_str()));
+
+    let opened = lib_wasm::open_claw_file(&armored, &key, Some("correct horse".into())).unwrap();
+    assert_eq!(opened, b"random data");
+
+    let file: ClawFile = armored.parse().unwrap();
+    assert_eq!(file.filename.as_deref(), Some("notes.txt"));
+    assert!(!file.is_expired());
+    let data = Vault::decipher(file.into_edata(key))
+        .with_passphrase(Some(Zeroizing::new("correct horse".into())))
+        .decrypt(&OsKeyProvider)
+        .and_then(|v| v.validate_and_get())
+        .unwrap();
+    assert_eq!(*data, b"random data");
+
+    let expired = armored.replace("Filename", "Expires: 2000-01-01T00:00:00Z\nFilename");
+    assert!(expired.parse::<ClawFile>().unwrap().is_expired());
+    assert!(armored.replace("Version: 5", "Version: 4").parse::<ClawFile>().is_err());
+    assert!(armored.replace("-----END CLAW FILE-----", "").parse::<ClawFile>().is_err());
+}
+
+#[test]
+fn ml_kem_768_acvp() {
+    use lib_core::vault::backend;
+
+    let vectors: serde_json::Value =
+        serde_json::from_str(include_str!("vectors/ml-kem-768.json")).unwrap();
+    let hex = |v: &serde_json::Value| -> Vec<u8> {
+        let v = v.as_str().unwrap();
+        (0..v.len()).step_by(2).map(|i| u8::from_str_radix(&v[i..i + 2], 16).unwrap()).collect()
+    };
+
+    for v in vectors["encapsulation"].as_array().unwrap() {
+        let m: [u8; 32] = hex(&v["m"]).try_into().unwrap();
+        let (c, k) = backend::ml_kem_768_encapsulate(&hex(&v["ek"]), &m).unwrap();
+        assert_eq!(c, hex(&v["c"]));
+        assert_eq!(k.to_vec(), hex(&v["k"]));
+    }
+
+    for v in vectors["keyGen"].as_array().unwrap() {
+        let seed: [u8; 64] = [hex(&v["d"]), hex(&v["z"])].concat().try_into().unwrap();
+        let ek = backend::ml_kem_768_public_key(&seed).unwrap();
+        assert_eq!(ek, hex(&v["ek"]));
+
+        // decapsulation is checked against encapsulation to the expanded key
+        let (mut c, k) = backend::ml_kem_768_encapsulate(&ek, &[7; 32]).unwrap();
+        assert_eq!(backend::ml_kem_768_decapsulate(&seed, &c).unwrap(), k);
+        c[0] ^= 1;
+        assert_ne!(backend::ml_kem_768_decapsulate(&seed, &c).unwrap(), k);
+    }
+
+    // the modulus check refuses encapsulation keys with unreduced coefficients
+    let mut ek = hex(&vectors["keyGen"][0]["ek"]);
+    ek[..2].copy_from_slice(&[0xff, 0xff]);
+    assert!(backend::ml_kem_768_encapsulate(&ek, &[7; 32]).is_err());
+}
+
+#[test]
+fn hybrid_recipient_vectors() {
+    use base64::Engine;
+    use lib_core::vault::hybrid;
+    use lib_core::{AppError, ErrType};
+    use std::cell::RefCell;
+
+    /// [`KeyProvider`] yielding the given bytes, then failing
+    struct FixedProvider(RefCell<Vec<u8>>);
+
+    impl KeyProvider for FixedProvider {
+        fn fill_bytes(&self, buf: &mut [u8]) -> AppResult<()> {
+            let mut bytes = self.0.borrow_mut();
+            if bytes.len() < buf.len() {
+                return Err(AppError::new(ErrType::VaultError, "Out of fixed bytes"));
+            }
+            buf.copy_from_slice(&bytes[..buf.len()]);
+            bytes.drain(..buf.len());
+            Ok(())
+        }
+    }
+
+    let vectors: serde_json::Value =
+        serde_json::from_str(include_str!("vectors/x25519-mlkem768.json")).unwrap();
+    let b64 = |v: &serde_json::Value| {
+        base64::engine::general_purpose::STANDARD.decode(v.as_str().unwrap()).unwrap()
+    };
+
+    for v in vectors["vectors"].as_array().unwrap() {
+        let private = hybrid::parse_private_key(v["private_key"].as_str().unwrap()).unwrap();
+        let public = hybrid::parse_public_key(v["public_key"].as_str().unwrap()).unwrap();
+        assert_eq!(hybrid::public_key(&private).unwrap(), public);
+
+        let (key, stanza) = (v["key"].as_str().unwrap(), v["stanza"].as_str().unwrap());
+        let provider = FixedProvider(RefCell::new(b64(&v["random"])));
+        assert_eq!(hybrid::seal(&provider, &public, key.as_bytes()).unwrap(), stanza);
+        assert_eq!(*hybrid::open(&OsKeyProvider, &private, stanza).unwrap(), key.as_bytes());
+
+        // flipping a bit of the ML-KEM ciphertext alone must break the stanza
+        let mut tampered = b64(&v["stanza"]);
+        tampered[100] ^= 1;
+        let tampered = hybrid::encode_key(&tampered);
+        assert!(hybrid::open(&OsKeyProvider, &private, &tampered).is_err());
+    }
+}
+
+#[test]
+fn recipient_suite() {
+    use lib_core::vault::{hybrid, recipient::RecipientSuite};
+
+    let (private, public) = hybrid::generate(&OsKeyProvider).unwrap();
+    let suite: RecipientSuite = "x25519-mlkem768".parse().unwrap();
+    assert_eq!(suite, RecipientSuite::X25519MlKem768);
+    assert_eq!(RecipientSuite::try_from(i16::from(suite)).unwrap(), suite);
+
+    let public = suite.canonical_public_key(&hybrid::encode_key(&public)).unwrap();
+    let stanza = suite.seal(&OsKeyProvider, &public, b"claw key").unwrap();
+    let opened = suite.open(&OsKeyProvider, &hybrid::encode_key(&*private), &stanza).unwrap();
+    assert_eq!(*opened, b"claw key");
+
+    // keys of one suite are rejected by the other
+    assert!(RecipientSuite::X25519.canonical_public_key(&public).is_err());
+    assert!(suite.canonical_public_key(AGE_RECIPIENT).is_err());
+    assert!("x448".parse::<RecipientSuite>().is_err());
+}